@@ -98,7 +98,7 @@ fn vdom_create_10000_with_30_elems(c: &mut Criterion) {
         for i in 0..10000 {
                 v.push((i, i.to_string()));
         }
-        edom::EDOM::render(edom::noop::ElementNode {tag:"body", generic_node: noop::Node {  }}, 
+        edom::EDOM::render(edom::noop::ElementNode {tag:"body".into(), generic_node: noop::Node {  }}, 
                 move |mut root| {
             root.for_each(v.iter_mut(), |(i, _)| *i, "span", 
                     |elem, span| {
@@ -118,7 +118,7 @@ fn vdom_create_10000_with_30_elems_swap20x(c: &mut Criterion) {
                 v.push((i, i.to_string()));
         }
         let e=edom::EDOM::render(
-                edom::noop::ElementNode {tag:"body", generic_node: noop::Node {  }}, 
+                edom::noop::ElementNode {tag:"body".into(), generic_node: noop::Node {  }}, 
                 move |mut root| {
             root.for_each(v.iter_mut(), |(i, _)| *i, "span", 
                     |elem, span| {
@@ -72,6 +72,7 @@ pub fn simpletodo() {
         root.ul(|ul| {
             ul.for_each(todolist.iter_mut(), |item| item.id, "li", |item,li|{
                 li.text(item.text.as_str());
+                li.multi_select(&mut item.labels, all_labels.iter().map(|label| (label.clone(), label.as_str())));
             });
         });
     });
@@ -9,6 +9,23 @@ enum ShowState {
     Active
 }
 
+impl Route for ShowState {
+    fn from_url(url: &Url) -> Self {
+        match url.segments().as_slice() {
+            ["active"] => ShowState::Active,
+            ["completed"] => ShowState::Completed,
+            _ => ShowState::All
+        }
+    }
+    fn to_url(&self) -> Url {
+        match self {
+            ShowState::All => Url::default(),
+            ShowState::Active => Url::from_segments(&["active"]),
+            ShowState::Completed => Url::from_segments(&["completed"])
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct TodoItem {
     id:Uuid,
@@ -17,9 +34,9 @@ struct TodoItem {
 }
 
 use edom;
+use edom::keyboard::Key;
+use edom::router::{Route, Router, Url};
 
-const ENTER_KEY: u32 = 13;
-const ESCAPE_KEY: u32 = 27;
 
 use wasm_bindgen::prelude::wasm_bindgen;
 
@@ -35,7 +52,7 @@ fn todomvc() {
     if let Some(data)=local_storage.get_item("todos-edom").unwrap() {
         todolist=serde_json::from_str(data.as_str()).unwrap();
     }
-    let mut show_state=ShowState::All;
+    let mut router=Router::<ShowState>::hash();
     let mut new_text=String::new();
     let mut editing = None;
     let mut edit_value = String::new();
@@ -44,19 +61,17 @@ fn todomvc() {
         root.class("todoapp");
         local_storage.set_item("todos-edom", serde_json::to_string(&todolist).unwrap().as_str()).unwrap();
         let num_active = todolist.iter().filter(|item| !item.completed).count();
+        let show_state=root.route(&mut router);
 
         root.header(|header| {
             header.h1().text("todos");
-            header.text_input(&mut new_text).class("new-todo")
-            .placeholder("What needs to be done?").autofocus(true).on("keydown", |e| {
-                if e.dyn_ref::<web_sys::KeyboardEvent>().unwrap().which() == ENTER_KEY {
-                    todolist.push(TodoItem {id: Uuid::new_v4(), completed: false, description: new_text.clone()});
-                    new_text.clear();
-                    web_sys::console::log_2(&"Enter".into(), &new_text.clone().into());
-                    e.target().unwrap().dyn_ref::<web_sys::HtmlElement>().unwrap().blur().unwrap();
-                    e.prevent_default();
-                }
-            });
+            let mut input=header.text_input(&mut new_text);
+            input.class("new-todo").placeholder("What needs to be done?").autofocus(true);
+            if input.key_pressed(Key::Enter) {
+                todolist.push(TodoItem {id: Uuid::new_v4(), completed: false, description: new_text.clone()});
+                new_text.clear();
+                input.get_dnode().dyn_ref::<web_sys::HtmlElement>().unwrap().blur().unwrap();
+            }
         }).class("header");
         let mut remove=None;
         let mut toggle_all = num_active==0;
@@ -94,30 +109,31 @@ fn todomvc() {
                             remove=Some(todo.id);
                         }
                     });
-                    li.render_element_if(Some(todo.id) == editing, "span", |li2| {
-                        li2.text_input(&mut edit_value).id("edit").class("edit").autofocus(true).on("keydown", |e| {
-                            let which=e.dyn_ref::<web_sys::KeyboardEvent>().unwrap().which();
-                            if which == ENTER_KEY {
+                    li.render_if(Some(todo.id) == editing, |li2| {
+                        let mut input=li2.text_input(&mut edit_value);
+                        input.id("edit").class("edit").autofocus(true);
+                        match input.keydown().map(|e| e.key) {
+                            Some(Key::Enter) => {
                                 todo.description=edit_value.clone();
                                 editing=None;
-                            } else if which == ESCAPE_KEY {
-                                editing = None;
-                            }
-                            e.target().unwrap().dyn_ref::<web_sys::HtmlElement>().unwrap().blur().unwrap();
-                        });
+                            },
+                            Some(Key::Escape) => editing=None,
+                            _ => return,
+                        }
+                        input.get_dnode().dyn_ref::<web_sys::HtmlElement>().unwrap().blur().unwrap();
                     });
                 });
             });
             if let Some(remove_id) = remove {
                 todolist.retain(|e| e.id != remove_id);
             }
-            render_footer(&mut section, &mut todolist, num_active, &mut show_state);
+            render_footer(&mut section, &mut todolist, num_active, &show_state);
         });
     });
 }
 
 fn render_footer<EN: edom::ElementNode>(container : &mut edom::Visitor<EN>, todolist :&mut Vec<TodoItem>,
-        num_active: usize, show_state: &mut ShowState) {
+        num_active: usize, show_state: &ShowState) {
     container.footer(|footer| {
         footer.class("footer");
         footer.span(|span| {
@@ -127,21 +143,11 @@ fn render_footer<EN: edom::ElementNode>(container : &mut edom::Visitor<EN>, todo
         });
         footer.ul(|ul| {
             ul.class("filters");
-            ul.li(|li| {
-                if li.a("#/", "All").class(if *show_state==ShowState::All {"selected"} else {""}).clicked() {
-                    *show_state=ShowState::All;
-                }
-            });
-            ul.li(|li| {
-                if li.a("#/active", "Active").class(if *show_state==ShowState::Active {"selected"} else {""}).clicked() {
-                    *show_state=ShowState::Active;
-                }
-            });
-            ul.li(|li| {
-                if li.a("#/completed", "Completed").class(if *show_state==ShowState::Completed {"selected"} else {""}).clicked() {
-                    *show_state=ShowState::Completed;
-                }
-            });
+            for (filter, text) in [(ShowState::All, "All"), (ShowState::Active, "Active"), (ShowState::Completed, "Completed")] {
+                ul.li(|li| {
+                    li.link(&filter, text).class(if *show_state==filter {"selected"} else {""});
+                });
+            }
         });
         footer.render_element_if(num_active < todolist.len(), "span", |footer| {
             if footer.button("Clear completed").class("clear-completed").clicked() {
@@ -1,47 +1,84 @@
 
 use std::{cell::RefCell, rc::Rc};
+use std::borrow::Cow;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// A tag, attribute or event name. Literals are borrowed, so only names built at runtime allocate.
+pub type Name = Cow<'static, str>;
 
 /// An abstraction for DOM node that contains associated types for other `dom` structs
-pub trait ElementNode : Sized {
+pub trait ElementNode : Sized + Clone {
     /// A generic node that can be text node or element node as well
     type GenericNode : GenericNode<TextNode=Self::TextNode, ElementNode=Self>;
     type TextNode : TextNode;
     type Document : Document<TextNode=Self::TextNode, ElementNode=Self>;
     type EventHandler : EventHandler<ElementNode=Self,Event=Self::Event>;
     type Event : Event;
+    type Executor : Executor;
     fn replace_text_child(&self, new: &Self::TextNode, old: &Self::TextNode);
     fn append_child(&self, child: &Self);
     fn insert_child_before(&self, child: &Self::GenericNode, next_sibling: Option<&Self::GenericNode>);
     fn append_child_after(&self, child: &Self, prev_sibling: &Self);
     fn remove_child(&self, child: &Self);
+    /// Removes a child element or text node.
+    fn remove_child_node(&self, child: &Self::GenericNode);
     fn prepend_child(&self, child: &Self);
     fn append_text_child(&self, child: &Self::TextNode);
+    fn insert_text_child_before(&self, child: &Self::TextNode, next_sibling: Option<&Self::GenericNode>);
     fn set_attribute(&self, name: &str, value: &str);
     fn get_attribute(&self, name: &str)->String;
     fn remove(&self);
-    fn new(tag: &'static str)->Self;
-    fn create_dnode_event_listener(&self, f : Rc<RefCell<dyn FnMut(u64, &'static str)>>, uid:u64, name:&'static str);
+    fn new(tag: impl Into<Name>)->Self;
+    fn create_dnode_event_listener(&self, f : Rc<RefCell<dyn FnMut(u64, String)>>, uid:u64, name:&str);
     fn deep_clone(&self)->Self;
     fn get_child_nodes(&self)->Vec<Self::GenericNode>;
     // Get selected child node (indexed from 0)
     fn get_child_node(&self, i:u32)->Option<Self::GenericNode>;
     fn set_text_content(&self, s:&str);
+    /// Concatenated text of all descendant text nodes.
+    fn text_content(&self)->String;
+    /// Text of the element as it's rendered, like `HTMLElement.innerText`: line breaks for `<br>` and
+    /// between block elements.
+    fn inner_text(&self)->String;
+    /// Replaces the children of the element with the lines of `s`, separated by `<br>` elements.
+    fn set_inner_text(&self, s: &str);
+    /// HTML of the children of the element.
+    fn inner_html(&self)->String;
+    /// Replaces the children of the element with the parsed `html`.
+    fn set_inner_html(&self, html: &str);
     fn into_generic_node(&self)->&Self::GenericNode;
     fn focus(&self);
+    /// Whether the element is the focused element of the document.
+    fn has_focus(&self)->bool;
+    /// Tag name of the element (may be upper case for HTML elements in the browser).
+    fn tag_name(&self)->String;
 }
 
-pub trait GenericNode  : Sized {
+pub trait GenericNode  : Sized + PartialEq {
     type TextNode : TextNode;
     type ElementNode : ElementNode;
     fn into_text_node(self)->Self::TextNode;
     fn into_element_node(self)->Self::ElementNode;
+    fn is_element(&self)->bool;
 }
 
 pub trait EventHandler {
     type ElementNode:ElementNode;
     type Event:Event;
+    /// A running timer that is cancelled when dropped.
+    type Timer;
     fn new(fire_event: Rc<RefCell<Box<dyn FnMut(u64, String, Self::Event)>>>)->Self;
     fn create_event_listener(&self, e: &Self::ElementNode, name: String);
+    /// Fires the event `name` for `uid` after `interval`, and after that in every `interval` if `repeat` is set.
+    fn create_timer(&self, uid: u64, name: String, interval: Duration, repeat: bool)->Self::Timer;
+    /// Time elapsed since an arbitrary fixed point, in the same clock as the timers.
+    fn now(&self)->Duration;
+    /// The scheduler that batches renders by default, see [`crate::EDOM::set_scheduler`].
+    fn default_scheduler()->Option<Rc<dyn Scheduler>> {
+        None
+    }
 }
 
 pub trait Document {
@@ -49,15 +86,41 @@ pub trait Document {
     type ElementNode : ElementNode;
     fn create_text_node(&self, text: &str)->Self::TextNode;
     fn new()->Self;
-    fn create_element(&self, tag: &'static str)->Self::ElementNode;
+    fn create_element(&self, tag: impl Into<Name>)->Self::ElementNode;
+    /// The focused element, if there is one besides the body.
+    fn active_element(&self)->Option<Self::ElementNode>;
     fn log_1(s: &str);
     fn log_2(s: &str, s2: &str);
 }
 
 pub trait TextNode {
     fn new(text: &str)->Self;
+    fn text_data(&self)->String;
 }
 
 pub trait Event {
     fn prevent_default(&self);
+    /// The data of a keyboard event, `None` for other events.
+    fn keyboard(&self)->Option<crate::keyboard::KeyboardEvent>;
+    /// The data of a mouse, pointer or wheel event, `None` for other events.
+    fn pointer(&self)->Option<crate::pointer::PointerEvent>;
+}
+
+
+/// A future that is run on the thread that spawned it.
+pub type LocalFuture = Pin<Box<dyn Future<Output=()>>>;
+
+/// Runs the futures spawned by [`crate::Visitor::spawn`].
+pub trait Executor {
+    /// Creates the executor of an [`crate::EDOM`] instance.
+    fn new()->Self;
+    /// Runs the future to completion later; it must not be polled before returning.
+    fn spawn_local(&self, future: LocalFuture);
+}
+
+/// Decides when the DOM updating render pass runs if renders are batched with
+/// [`crate::EDOM::set_scheduler`].
+pub trait Scheduler {
+    /// Calls `callback` once in the next frame; it must not be called before returning.
+    fn request_frame(&self, callback: Box<dyn FnOnce()>);
 }
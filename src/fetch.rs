@@ -0,0 +1,305 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use super::task::Task;
+
+/// The error of a failed request.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The request couldn't be sent or the response couldn't be read.
+    Network(String),
+    /// The server responded with a status that is not 2xx. Contains the status and the body.
+    Status(u16, String),
+    /// The response body couldn't be decoded.
+    Decode(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+        match self {
+            Error::Network(e)=>write!(f, "Network error: {}", e),
+            Error::Status(status, _)=>write!(f, "HTTP status {}", status),
+            Error::Decode(e)=>write!(f, "Decoding error: {}", e),
+        }
+    }
+}
+
+/// State of a request made with a [`Client`], see [`Task`].
+pub type Fetch<T> = Task<T, Error>;
+
+/// An HTTP request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    /// HTTP method, like `GET` or `POST`.
+    pub method: String,
+    /// The URL, relative to the page in the browser.
+    pub url: String,
+    /// Request headers.
+    pub headers: Vec<(String, String)>,
+    /// Request body.
+    pub body: Option<String>,
+}
+
+/// An HTTP response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    /// HTTP status code.
+    pub status: u16,
+    /// Response body.
+    pub body: String,
+}
+
+impl Response {
+    /// Creates a response.
+    pub fn new(status: u16, body: impl Into<String>)->Self {
+        Response { status, body: body.into() }
+    }
+    /// Whether the status is 2xx.
+    pub fn is_ok(&self)->bool {
+        (200..300).contains(&self.status)
+    }
+    /// Decodes the body as JSON.
+    pub fn json<T: DeserializeOwned>(&self)->Result<T, Error> {
+        serde_json::from_str(&self.body).map_err(|e| Error::Decode(e.to_string()))
+    }
+}
+
+/// The future returned by [`Transport::send`].
+pub type ResponseFuture = Pin<Box<dyn Future<Output=Result<Response, Error>>>>;
+
+/// Sends requests for a [`Client`].
+pub trait Transport {
+    /// Sends the request. The future completes when the whole body has arrived.
+    fn send(&self, request: Request)->ResponseFuture;
+}
+
+/// Sends requests using `window.fetch`.
+pub struct BrowserTransport {
+}
+
+fn js_error(e: JsValue)->Error {
+    Error::Network(format!("{:?}", e))
+}
+
+impl Transport for BrowserTransport {
+    fn send(&self, request: Request)->ResponseFuture {
+        Box::pin(async move {
+            let init=web_sys::RequestInit::new();
+            init.set_method(&request.method);
+            init.set_mode(web_sys::RequestMode::Cors);
+            if let Some(body)=&request.body {
+                init.set_body(&JsValue::from_str(body));
+            }
+            let js_request=web_sys::Request::new_with_str_and_init(&request.url, &init).map_err(js_error)?;
+            for (name, value) in &request.headers {
+                js_request.headers().set(name, value).map_err(js_error)?;
+            }
+            let window=web_sys::window().ok_or_else(|| Error::Network("No window".to_string()))?;
+            let response: web_sys::Response=JsFuture::from(window.fetch_with_request(&js_request)).await
+                .map_err(js_error)?.dyn_into().map_err(js_error)?;
+            let body=JsFuture::from(response.text().map_err(js_error)?).await.map_err(js_error)?;
+            Ok(Response { status: response.status(), body: body.as_string().unwrap_or_default() })
+        })
+    }
+}
+
+type MockHandler = Box<dyn Fn(&Request)->Result<Response, Error>>;
+
+/// Answers requests with a function instead of using the network, for testing natively.
+/// The responses are ready when the executor first polls them.
+pub struct MockTransport {
+    handler: MockHandler,
+    requests: Rc<RefCell<Vec<Request>>>,
+}
+
+impl MockTransport {
+    /// Creates a transport that answers with `handler`.
+    pub fn new<H>(handler: H)->Self where H: Fn(&Request)->Result<Response, Error> + 'static {
+        MockTransport { handler: Box::new(handler), requests: Rc::new(RefCell::new(Vec::new())) }
+    }
+    /// The requests sent so far.
+    pub fn requests(&self)->Rc<RefCell<Vec<Request>>> {
+        self.requests.clone()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request: Request)->ResponseFuture {
+        let response=(self.handler)(&request);
+        self.requests.borrow_mut().push(request);
+        Box::pin(std::future::ready(response))
+    }
+}
+
+/// Creates requests that are sent with a [`Transport`]. The futures returned by the
+/// `send*` methods can be run with [`crate::Visitor::spawn`]:
+/// ```
+/// #[derive(serde::Deserialize)]
+/// struct User {name: String}
+///
+/// let client=edom::fetch::Client::browser();
+/// let mut users=edom::fetch::Fetch::<Vec<User>>::new();
+/// edom::noop::render(move |mut root| {
+///     if users.is_idle() {
+///         root.spawn(&mut users, client.get("/api/users").send_json());
+///     }
+///     match &*users.state() {
+///         edom::task::TaskState::Ready(users)=>root.ul(|ul| {
+///             ul.for_each(users.iter(), |u| u.name.clone(), "li", |u, li| li.text(&u.name));
+///         }),
+///         edom::task::TaskState::Err(e)=>root.div(|div| div.text(e.to_string().as_str())),
+///         _=>root.div(|div| div.text("Loading...")),
+///     };
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Client {
+    transport: Rc<dyn Transport>,
+}
+
+impl Client {
+    /// Creates a client that sends the requests with `transport`.
+    pub fn new<T: Transport + 'static>(transport: T)->Self {
+        Client { transport: Rc::new(transport) }
+    }
+    /// Creates a client that uses `window.fetch`.
+    pub fn browser()->Self {
+        Self::new(BrowserTransport {})
+    }
+    /// Starts building a request.
+    pub fn request(&self, method: &str, url: &str)->RequestBuilder {
+        RequestBuilder {
+            transport: self.transport.clone(),
+            request: Request { method: method.to_string(), url: url.to_string(), headers: Vec::new(), body: None },
+        }
+    }
+    /// Starts building a `GET` request.
+    pub fn get(&self, url: &str)->RequestBuilder {
+        self.request("GET", url)
+    }
+    /// Starts building a `POST` request.
+    pub fn post(&self, url: &str)->RequestBuilder {
+        self.request("POST", url)
+    }
+}
+
+/// A request created by [`Client`].
+pub struct RequestBuilder {
+    transport: Rc<dyn Transport>,
+    request: Request,
+}
+
+impl RequestBuilder {
+    /// Adds a header.
+    pub fn header(mut self, name: &str, value: &str)->Self {
+        self.request.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+    /// Sets the body.
+    pub fn body(mut self, body: impl Into<String>)->Self {
+        self.request.body=Some(body.into());
+        self
+    }
+    /// Sets the body to `value` encoded as JSON.
+    pub fn json<T: Serialize + ?Sized>(self, value: &T)->Self {
+        let body=serde_json::to_string(value).expect("Value can't be encoded as JSON");
+        self.header("Content-Type", "application/json").body(body)
+    }
+    /// Sends the request and returns the response whatever its status is.
+    pub fn send(self)->ResponseFuture {
+        self.transport.send(self.request)
+    }
+    /// Sends the request and returns the body if the status is 2xx.
+    pub fn send_text(self)->impl Future<Output=Result<String, Error>> {
+        let response=self.send();
+        async move {
+            let response=response.await?;
+            if !response.is_ok() {
+                return Err(Error::Status(response.status, response.body));
+            }
+            Ok(response.body)
+        }
+    }
+    /// Sends the request and decodes the body as JSON if the status is 2xx.
+    pub fn send_json<T: DeserializeOwned>(self)->impl Future<Output=Result<T, Error>> {
+        let body=self.send_text();
+        async move {
+            serde_json::from_str(&body.await?).map_err(|e| Error::Decode(e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::TaskState;
+    use crate::testing::TestApp;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Item {
+        name: String,
+    }
+
+    fn items_app(client: Client)->TestApp {
+        let mut items=Fetch::<Vec<Item>>::new();
+        let mut saved=Fetch::<String>::new();
+        TestApp::new(move |mut root| {
+            if items.is_idle() {
+                root.spawn(&mut items, client.get("/items").send_json());
+            }
+            if root.button("Save").clicked() {
+                root.spawn(&mut saved, client.post("/items").json(&Item { name: "c".to_string() }).send_text());
+            }
+            let text=match &*items.state() {
+                TaskState::Ready(items)=>items.iter().map(|i| i.name.as_str()).collect::<Vec<_>>().join(","),
+                TaskState::Err(e)=>e.to_string(),
+                _=>"Loading".to_string(),
+            };
+            root.div(|div| div.text(text.as_str()));
+            root.render_element_if(saved.ready().is_some(), "p", |p| p.text("Saved"));
+        })
+    }
+
+    #[test]
+    fn loading_and_success() {
+        let transport=MockTransport::new(|request| match request.method.as_str() {
+            "GET"=>Ok(Response::new(200, r#"[{"name": "a"}, {"name": "b"}]"#)),
+            _=>Ok(Response::new(201, "")),
+        });
+        let requests=transport.requests();
+        let app=items_app(Client::new(transport));
+        assert_eq!("Loading", app.find_by_tag("div").unwrap().text);
+        app.run_until_stalled();
+        assert_eq!("a,b", app.find_by_tag("div").unwrap().text);
+        assert!(app.find_by_tag("p").is_none());
+        app.click(&app.find_by_text("Save").unwrap());
+        app.run_until_stalled();
+        assert_eq!("Saved", app.find_by_tag("p").unwrap().text);
+        let post=&requests.borrow()[1];
+        assert_eq!(Some(r#"{"name":"c"}"#), post.body.as_deref());
+        assert_eq!(vec![("Content-Type".to_string(), "application/json".to_string())], post.headers);
+    }
+
+    #[test]
+    fn errors() {
+        let app=items_app(Client::new(MockTransport::new(|_| Ok(Response::new(500, "oops")))));
+        app.run_until_stalled();
+        assert_eq!("HTTP status 500", app.find_by_tag("div").unwrap().text);
+
+        let app=items_app(Client::new(MockTransport::new(|_| Ok(Response::new(200, "{")))));
+        app.run_until_stalled();
+        assert!(app.find_by_tag("div").unwrap().text.starts_with("Decoding error"));
+
+        let app=items_app(Client::new(MockTransport::new(|_| Err(Error::Network("offline".to_string())))));
+        app.run_until_stalled();
+        assert_eq!("Network error: offline", app.find_by_tag("div").unwrap().text);
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+
+use super::dom;
+use super::dom::Event;
+use super::visitor::Visitor;
+
+/// The key value of a keyboard event, with variants for the keys that applications usually handle.
+/// Other keys are kept by their key value, like `"F1"` or `"Shift"`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// The Enter or Return key.
+    Enter,
+    /// The Escape key.
+    Escape,
+    /// The Tab key.
+    Tab,
+    /// The Backspace key.
+    Backspace,
+    /// The Delete key.
+    Delete,
+    /// The up arrow key.
+    ArrowUp,
+    /// The down arrow key.
+    ArrowDown,
+    /// The left arrow key.
+    ArrowLeft,
+    /// The right arrow key.
+    ArrowRight,
+    /// The Home key.
+    Home,
+    /// The End key.
+    End,
+    /// The Page Up key.
+    PageUp,
+    /// The Page Down key.
+    PageDown,
+    /// A key that produces a character, including `' '` for the space bar.
+    Char(char),
+    /// Any other key value.
+    Other(String),
+}
+
+impl Key {
+    /// The key value as it's used by the DOM `KeyboardEvent.key` property.
+    pub fn as_str(&self)->std::borrow::Cow<'_, str> {
+        let s=match self {
+            Key::Enter=>"Enter",
+            Key::Escape=>"Escape",
+            Key::Tab=>"Tab",
+            Key::Backspace=>"Backspace",
+            Key::Delete=>"Delete",
+            Key::ArrowUp=>"ArrowUp",
+            Key::ArrowDown=>"ArrowDown",
+            Key::ArrowLeft=>"ArrowLeft",
+            Key::ArrowRight=>"ArrowRight",
+            Key::Home=>"Home",
+            Key::End=>"End",
+            Key::PageUp=>"PageUp",
+            Key::PageDown=>"PageDown",
+            Key::Char(c)=>return c.to_string().into(),
+            Key::Other(s)=>s.as_str(),
+        };
+        s.into()
+    }
+}
+
+impl From<&str> for Key {
+    /// Parses a DOM key value. Old browsers use `"Esc"`, `"Del"` and `"Up"` style names for some keys.
+    fn from(key: &str)->Self {
+        match key {
+            "Enter"=>Key::Enter,
+            "Escape" | "Esc"=>Key::Escape,
+            "Tab"=>Key::Tab,
+            "Backspace"=>Key::Backspace,
+            "Delete" | "Del"=>Key::Delete,
+            "ArrowUp" | "Up"=>Key::ArrowUp,
+            "ArrowDown" | "Down"=>Key::ArrowDown,
+            "ArrowLeft" | "Left"=>Key::ArrowLeft,
+            "ArrowRight" | "Right"=>Key::ArrowRight,
+            "Home"=>Key::Home,
+            "End"=>Key::End,
+            "PageUp"=>Key::PageUp,
+            "PageDown"=>Key::PageDown,
+            "Spacebar"=>Key::Char(' '),
+            _=>{
+                let mut chars=key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None)=>Key::Char(c),
+                    _=>Key::Other(key.to_string()),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+        f.write_str(&self.as_str())
+    }
+}
+
+/// The modifier keys that were held down during a keyboard event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    /// The Control key.
+    pub ctrl: bool,
+    /// The Shift key.
+    pub shift: bool,
+    /// The Alt (Option) key.
+    pub alt: bool,
+    /// The Meta (Windows or Command) key.
+    pub meta: bool,
+}
+
+impl Modifiers {
+    /// Whether no modifier key was held down.
+    pub fn is_empty(&self)->bool {
+        *self == Modifiers::default()
+    }
+}
+
+/// The data of a keyboard event that doesn't depend on the DOM backend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyboardEvent {
+    /// The key value, which depends on the keyboard layout and the modifiers.
+    pub key: Key,
+    /// The physical key, like `"KeyA"` or `"Enter"`. Empty if it's not known.
+    pub code: String,
+    /// The modifier keys that were held down.
+    pub modifiers: Modifiers,
+    /// Whether the event is repeated because the key is being held down.
+    pub repeat: bool,
+}
+
+impl KeyboardEvent {
+    /// An event of pressing `key` without modifiers.
+    pub fn new(key: Key)->Self {
+        KeyboardEvent { key, code: String::new(), modifiers: Modifiers::default(), repeat: false }
+    }
+
+    /// Sets the modifier keys.
+    pub fn with_modifiers(mut self, modifiers: Modifiers)->Self {
+        self.modifiers=modifiers;
+        self
+    }
+}
+
+impl<'d, 'e, 'c, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Returns the keyboard event in the render pass that is caused by a `keydown` event on the
+    /// current element.
+    pub fn keydown(&'c mut self)->Option<KeyboardEvent> {
+        let mut r=None;
+        self.on("keydown", |e| r=e.keyboard());
+        r
+    }
+
+    /// Returns true if `key` was pressed on the current element (with any modifiers):
+    /// ```
+    /// use edom::keyboard::Key;
+    /// let (mut text, mut saved)=(String::new(), String::new());
+    /// let app=edom::testing::TestApp::new(move |mut root| {
+    ///     let mut input=root.text_input(&mut text);
+    ///     if input.key_pressed(Key::Enter) {
+    ///         saved=text.clone();
+    ///     }
+    ///     if input.key_pressed(Key::Escape) {
+    ///         saved.clear();
+    ///     }
+    ///     root.text(saved.as_str());
+    /// });
+    /// let input=app.find_by_tag("input").unwrap();
+    /// app.input(&input, "milk");
+    /// app.keydown(&input, "Enter");
+    /// assert!(app.html().ends_with("milk"));
+    /// ```
+    pub fn key_pressed(&'c mut self, key: Key)->bool {
+        self.keydown().is_some_and(|e| e.key == key)
+    }
+
+    /// Returns true if `key` was pressed on the current element with exactly the `modifiers` held down.
+    pub fn shortcut_pressed(&'c mut self, modifiers: Modifiers, key: Key)->bool {
+        self.keydown().is_some_and(|e| e.key == key && e.modifiers == modifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestApp;
+
+    #[test]
+    fn keys() {
+        for key in [Key::Enter, Key::Escape, Key::ArrowLeft, Key::PageDown, Key::Char('a'), Key::Char(' '),
+                Key::Other("F1".to_string())] {
+            assert_eq!(key, Key::from(key.as_str().as_ref()));
+        }
+        assert_eq!(Key::Escape, Key::from("Esc"));
+        assert_eq!(Key::Char('é'), Key::from("é"));
+        assert_eq!("Shift", Key::from("Shift").to_string());
+    }
+
+    #[test]
+    fn shortcuts() {
+        let mut log=Vec::new();
+        let app=TestApp::new(move |mut root| {
+            root.div(|div| {
+                div.id("editor");
+                let ctrl=Modifiers { ctrl: true, ..Modifiers::default() };
+                if div.shortcut_pressed(ctrl, Key::Char('z')) {
+                    log.push("undo".to_string());
+                }
+                if div.key_pressed(Key::Delete) {
+                    log.push("delete".to_string());
+                }
+                if let Some(e)=div.keydown() {
+                    log.push(format!("{} {} {}", e.key, e.code, e.repeat));
+                }
+                div.text(log.join(",").as_str());
+            });
+        });
+        let editor=app.find_by_id("editor").unwrap();
+        app.keydown(&editor, "z");
+        app.keydown_with(&editor, KeyboardEvent::new(Key::Char('z'))
+            .with_modifiers(Modifiers { ctrl: true, ..Modifiers::default() }));
+        app.keydown_with(&editor, KeyboardEvent { code: "Delete".to_string(), repeat: true,
+            ..KeyboardEvent::new(Key::Delete) });
+        assert_eq!("z  false,undo,z  false,delete,Delete Delete true", app.find_by_id("editor").unwrap().text);
+        // The keydown listener is added once even though it's listened to three times.
+        assert_eq!(vec!["keydown".to_string()], app.dnode(&editor).unwrap().listeners());
+    }
+}
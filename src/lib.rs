@@ -57,7 +57,7 @@ doc = "**Doc images not enabled**. Compile with feature `doc-images` and Rust ve
 extern crate console_error_panic_hook;
 extern crate smallstr;
 extern crate embed_doc_image;
-pub use dom::{EventHandler, Document};
+pub use dom::{EventHandler, Document, Executor};
 
 /// An abstraction for DOM operations (collection of traits)
 pub mod dom;
@@ -80,13 +80,35 @@ pub struct EDOM<EN> where EN:dom::ElementNode {
     create: bool,
     document: EN::Document,
     pub fire_event: Rc<RefCell<Box<dyn FnMut(u64, String, EN::Event)>>>,
+    /// Runs the render function twice like [`EDOM::fire_event`], but without an event; the second
+    /// pass is requested from the scheduler if renders are batched.
+    /// Used when something outside of the DOM (like a finished future) changed the state.
+    pub rerender: Rc<RefCell<Box<dyn FnMut()>>>,
+    render_frame: Rc<RefCell<Box<dyn FnMut()>>>,  // The DOM updating pass requested from the scheduler.
     clone_for_each: bool,  // Clone node for for_each instead of building up the DOM tree.
     nodes_attached: u64,
     use_partial_clone: bool,
     root: Option<vdom::Element<EN>>,
-    event_handler: EN::EventHandler
+    event_handler: EN::EventHandler,
+    hydrating: bool,  // Bind to existing DOM nodes in the first render instead of creating them.
+    hydration_mismatches: Vec<String>,
+    invalid_names: Vec<String>,  // Tag and attribute names from data that were skipped.
+    executor: EN::Executor,
+    scheduler: Option<Rc<dyn dom::Scheduler>>,  // Batch the DOM updating render passes if set.
+    frame_requested: bool,
+    animation_frame_requested: bool,
+    animation_frame_timer: Option<<EN::EventHandler as dom::EventHandler>::Timer>,
+    history: Option<Rc<dyn router::History>>,  // Used by links, set by the first `Visitor::route`.
+    dragged: Option<u64>,  // Uid of the row of a sortable list that is being dragged.
+    focus_request: Option<EN>,  // Focused after the DOM updating pass, see `Visitor::request_focus`.
+    focused: Option<u64>,  // Uid of the focused element, tracked by `Visitor::has_focus`.
+    inserting: bool,  // The visited element was inserted into the DOM in this pass.
 }
 
+// Uid of the timer that re-renders animations if there's no scheduler; no element has it.
+const ANIMATION_FRAME_UID: u64 = u64::MAX;
+pub(crate) const ANIMATION_FRAME: std::time::Duration = std::time::Duration::from_millis(16);
+
 impl<EN> EDOM<EN> where EN:dom::ElementNode {
     fn next_uid(&mut self)->u64 {
         let r=self.last_uid;
@@ -102,27 +124,86 @@ impl<EN> EDOM<EN> where EN:dom::ElementNode {
         self.create=false;
     }
 
-    pub fn render<F>(root: EN, mut f:F)->Rc<RefCell<EDOM<EN>>>
+    pub fn render<F>(root: EN, f:F)->Rc<RefCell<EDOM<EN>>>
+            where EN:dom::ElementNode + 'static, F:FnMut(Visitor<EN>) + 'static {
+        Self::render_or_hydrate(root, f, false)
+    }
+
+    /// Like [`EDOM::render`], but the children of `root` are expected to be already rendered
+    /// (for example by [`ssr::render_to_string`]). The first render binds the [`vdom`] to the
+    /// existing DOM nodes and attaches the event listeners instead of creating new nodes.
+    /// Where the DOM is different from what the render function produces, the DOM is corrected
+    /// and the difference is logged and recorded in [`EDOM::hydration_mismatches`].
+    pub fn hydrate<F>(root: EN, f:F)->Rc<RefCell<EDOM<EN>>>
+            where EN:dom::ElementNode + 'static, F:FnMut(Visitor<EN>) + 'static {
+        Self::render_or_hydrate(root, f, true)
+    }
+
+    fn render_or_hydrate<F>(root: EN, f:F, hydrating: bool)->Rc<RefCell<EDOM<EN>>>
             where EN:dom::ElementNode + 'static, F:FnMut(Visitor<EN>) + 'static {
         let el=vdom::Element::new("body", Some(root), 0);
         let mut edom : EDOM<EN>=EDOM::new(el);
         assert_eq!(0, edom.next_uid());
-        edom.render_once(&mut f);
+        let f=Rc::new(RefCell::new(f));
+        edom.hydrating=hydrating;
+        edom.render_once(&mut *f.borrow_mut());
+        if hydrating {
+            edom.remove_unhydrated_nodes();
+        }
+        edom.hydrating=false;
+        edom.spawn_focus_request();
 
         let fire_event=edom.fire_event.clone();
+        let rerender=edom.rerender.clone();
+        let render_frame=edom.render_frame.clone();
         let edomrc : Rc<RefCell<EDOM<EN>>>=Rc::new(RefCell::new(edom));
         let moved_edomrc=edomrc.clone();
+        let moved_f=f.clone();
         *fire_event.borrow_mut()=Box::new(move |a:u64, b:String, e:EN::Event| {
-            // EN::Document::log_2(a.to_string().as_str(), b.as_str());
+            {
+                let mut edom=moved_edomrc.borrow_mut();
+                let mut f=moved_f.borrow_mut();
+                edom.nodes_attached=0;
+                if a==ANIMATION_FRAME_UID {
+                    edom.animation_frame_requested=false;
+                }
+                edom.firing_event=Some((a, b, e));
+                edom.render_once(&mut *f);
+                edom.firing_event=None;
+                match edom.scheduler.clone() {
+                    None => {
+                        edom.render_once(&mut *f);
+                        edom.spawn_focus_request();
+                    },
+                    Some(scheduler) => edom.request_frame(&scheduler),
+                }
+            }
+        });
+        let moved_edomrc=edomrc.clone();
+        let moved_f=f.clone();
+        *render_frame.borrow_mut()=Box::new(move || {
             let mut edom=moved_edomrc.borrow_mut();
-            edom.nodes_attached=0;
-            edom.firing_event=Some((a, b, e));
-            edom.render_once(&mut f);
-            edom.firing_event=None;
-            edom.render_once(&mut f);
-            // EN::Document::log_2("Nodes attached in render:", edom.nodes_attached.to_string().as_str());
+            edom.frame_requested=false;
+            edom.animation_frame_requested=false;
+            edom.render_once(&mut *moved_f.borrow_mut());
+            edom.spawn_focus_request();
+        });
+        let moved_edomrc=edomrc.clone();
+        *rerender.borrow_mut()=Box::new(move || {
+            let mut edom=moved_edomrc.borrow_mut();
+            let mut f=f.borrow_mut();
+            edom.render_once(&mut *f);
+            match edom.scheduler.clone() {
+                None => {
+                    edom.render_once(&mut *f);
+                    edom.spawn_focus_request();
+                },
+                Some(scheduler) => edom.request_frame(&scheduler),
+            }
         });
         std::mem::forget(fire_event);
+        std::mem::forget(rerender);
+        std::mem::forget(render_frame);
         edomrc
    }
 
@@ -132,14 +213,106 @@ impl<EN> EDOM<EN> where EN:dom::ElementNode {
                     web_sys::console::log_3(&"rc".into(), &a.to_string().into(), 
                     &b.into()))));
         let fe2=fire_event.clone();
-        EDOM {fire_event, firing_event: None, last_uid: 0, create: true, document:EN::Document::new(),
+        let rerender:Rc<RefCell<Box<dyn FnMut()>>>=Rc::new(RefCell::new(Box::new(|| ())));
+        let render_frame:Rc<RefCell<Box<dyn FnMut()>>>=Rc::new(RefCell::new(Box::new(|| ())));
+        EDOM {fire_event, rerender, render_frame, firing_event: None, last_uid: 0, create: true, document:EN::Document::new(),
             root: Some(root),
             nodes_attached: 0,
             clone_for_each: true, 
             use_partial_clone: true,
-            event_handler: EN::EventHandler::new(fe2)
+            event_handler: EN::EventHandler::new(fe2),
+            hydrating: false,
+            hydration_mismatches: Vec::new(),
+            invalid_names: Vec::new(),
+            executor: EN::Executor::new(),
+            scheduler: <EN::EventHandler as dom::EventHandler>::default_scheduler(),
+            frame_requested: false,
+            animation_frame_requested: false,
+            animation_frame_timer: None,
+            history: None,
+            dragged: None,
+            focus_request: None,
+            focused: None,
+            inserting: false,
         }
     }
+
+    /// Re-renders the application in the next frame, using the scheduler if renders are batched
+    /// and a timer otherwise. Used by animations that change in every frame.
+    pub fn request_animation_frame(&mut self) {
+        if self.animation_frame_requested {
+            return;
+        }
+        self.animation_frame_requested=true;
+        match self.scheduler.clone() {
+            // The frame of the batched events renders the animation as well.
+            Some(scheduler) => self.request_frame(&scheduler),
+            None => {
+                self.animation_frame_timer=Some(dom::EventHandler::create_timer(&self.event_handler,
+                    ANIMATION_FRAME_UID, "edom:frame".to_string(), ANIMATION_FRAME, false));
+            }
+        }
+    }
+
+    /// Requests the DOM updating render pass from `scheduler` if it's not requested yet.
+    fn request_frame(&mut self, scheduler: &Rc<dyn dom::Scheduler>) {
+        if self.frame_requested {
+            return;
+        }
+        self.frame_requested=true;
+        let render_frame=self.render_frame.clone();
+        scheduler.request_frame(Box::new(move || render_frame.borrow_mut()()));
+    }
+
+    /// Focuses the element requested by [`Visitor::request_focus`] after the running event handler
+    /// returns. Focusing dispatches focus and blur events synchronously in the browser, and they
+    /// couldn't be handled while the render function is running.
+    fn spawn_focus_request(&mut self) where EN: 'static {
+        if let Some(dnode)=self.focus_request.take() {
+            self.executor.spawn_local(Box::pin(async move { dnode.focus() }));
+        }
+    }
+
+    /// Batches renders: after an event only the pass that runs the event handlers is done
+    /// immediately, and the pass that updates the DOM with the state changed by them is requested
+    /// from `scheduler`, once for all the events that happen before the frame.
+    /// `None` switches back to rendering twice for every event. The wasm backend batches renders
+    /// into animation frames by default, other backends render twice.
+    pub fn set_scheduler(&mut self, scheduler: Option<Rc<dyn dom::Scheduler>>) {
+        self.scheduler=scheduler;
+    }
+
+    /// Differences found between the existing DOM and the first render in [`EDOM::hydrate`].
+    pub fn hydration_mismatches(&self)->&[String] {
+        &self.hydration_mismatches
+    }
+
+    /// Removes the DOM nodes that the first render didn't bind to, which are after the children of
+    /// an element.
+    fn remove_unhydrated_nodes(&mut self) {
+        let mut mismatches=Vec::new();
+        self.get_root().remove_unhydrated_children(&mut mismatches);
+        for message in mismatches {
+            self.report_hydration_mismatch(message);
+        }
+    }
+
+    fn report_hydration_mismatch(&mut self, message: String) {
+        EN::Document::log_2("Hydration mismatch:", message.as_str());
+        self.hydration_mismatches.push(message);
+    }
+
+    /// Tag and attribute names that weren't valid, for example because they came from data.
+    /// Elements with invalid tag names are rendered as `div`, and attributes with invalid names
+    /// are not set.
+    pub fn invalid_names(&self)->&[String] {
+        &self.invalid_names
+    }
+
+    fn report_invalid_name(&mut self, message: String) {
+        EN::Document::log_2("Invalid name:", message.as_str());
+        self.invalid_names.push(message);
+    }
     fn test_fire_event(&mut self, uid: u64, name: &str , event: EN::Event) {
         let fire_event=self.fire_event.clone();
         fire_event.borrow_mut()(uid, name.to_string(), event);
@@ -151,12 +324,42 @@ impl<EN> EDOM<EN> where EN:dom::ElementNode {
 
 /// A no-operation implementation of [`dom`] operations for testing and outputting HTML.
 pub mod noop;  
+/// An in-memory implementation of [`dom`] traits that keeps the whole tree, for testing natively.
+pub mod memdom;
+/// Server side rendering of the [`vdom`] to HTML.
+pub mod ssr;
+/// Futures that re-render the application when they complete.
+pub mod task;
+/// Timers that re-render the application when they fire.
+pub mod timer;
+/// Typed remote function calls over [`fetch`], with a server side dispatcher.
+pub mod rpc;
+/// Routing between the pages of the application using the URL.
+pub mod router;
+/// Backend independent keyboard events.
+pub mod keyboard;
+/// Backend independent mouse, pointer and wheel events.
+pub mod pointer;
+/// Reordering the rows of keyed lists with drag and drop.
+pub mod sortable;
+/// State of reusable widgets that is kept in the vdom between renders.
+pub mod state;
+/// Skipping the render of subtrees that didn't change.
+pub mod memo;
+/// Enter and leave transitions of conditionally rendered elements.
+pub mod transition;
+/// Values that animate smoothly to their targets.
+pub mod motion;
+/// HTTP requests whose results are shown with [`task::Task`].
+pub mod fetch;
+/// Simulating user interaction with an application rendered into [`memdom`].
+pub mod testing;
 /// Functions for manipulations that are specific to HTML in the [`visitor`].
 pub mod visitor_html;
 
 #[test]
 fn test_create() {
-    EDOM::render( noop::ElementNode {tag:"body", generic_node: noop::Node {  }}, move |mut root| {
+    EDOM::render( noop::ElementNode {tag:"body".into(), generic_node: noop::Node {  }}, move |mut root| {
         assert_eq!(0, root.element.children.len());
         root.div(|main|{
             assert_eq!(0, main.element.children.len());
@@ -173,7 +376,7 @@ fn test_create() {
 
 #[test]
 fn test_nodes_attached() {
-    let nobody=noop::ElementNode {tag:"body", generic_node: noop::Node {  }};
+    let nobody=noop::ElementNode {tag:"body".into(), generic_node: noop::Node {  }};
     let edom=EDOM::render(nobody, 
             move |mut root| {
         root.div(|main|{
@@ -215,7 +418,7 @@ fn test_nodes_attached() {
 #[test]
 fn test_swap_rows() {
     let mut v:Vec<u64>=vec![1,2,3,4];
-    let edom=EDOM::render(noop::ElementNode {tag:"body", generic_node: noop::Node {  }}, move |mut root| {
+    let edom=EDOM::render(noop::ElementNode {tag:"body".into(), generic_node: noop::Node {  }}, move |mut root| {
         let mut button=root.button("Swap rows");
         assert_eq!(1, button.element.uid);
         if button.clicked() {
@@ -258,7 +461,7 @@ fn test_swap_rows() {
 #[test]
 fn test_remove_row() {
     let mut v:Vec<u64>=vec![1,2,3,4,5,6,7,8];
-    let edom=EDOM::render(noop::ElementNode {tag:"body", generic_node: noop::Node {  }}, move |mut root| {
+    let edom=EDOM::render(noop::ElementNode {tag:"body".into(), generic_node: noop::Node {  }}, move |mut root| {
         let mut button=root.button("Swap rows");
         assert_eq!(1, button.element.uid);
         if button.clicked() {
@@ -286,7 +489,7 @@ fn test_remove_row() {
 #[test]
 fn test_render_if() {
     let mut button_clicked=false;
-    let edom=EDOM::render(noop::ElementNode {tag:"body", generic_node: noop::Node {  }}, move |mut root| {
+    let edom=EDOM::render(noop::ElementNode {tag:"body".into(), generic_node: noop::Node {  }}, move |mut root| {
         root.render_element_if(true, "span", |span| {
             let mut button=span.button("hello");
             assert_eq!(2, button.element.uid);
@@ -302,4 +505,93 @@ fn test_render_if() {
     let edom=(*edom).borrow_mut();
     let root=edom.get_root();
     assert_eq!("true", root.children[1].get_text());
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+fn hydrated_app<EN: ElementNode>(mut root: Visitor<EN>, count: &mut u64, items: &mut Vec<u64>) {
+    root.h1().text("Items");
+    if root.button("Add").clicked() {
+        *count+=1;
+        items.push(*count);
+    }
+    root.text("Count: ");
+    root.text(count.to_string().as_str());
+    root.element("ul").for_each(items.iter_mut(), |i| **i, "li", |i, li| {
+        li.text(i.to_string().as_str());
+    });
+    root.render_element_if(*count > 0, "p", |p| p.text("not empty"));
+}
+
+#[test]
+fn test_hydrate() {
+    use dom::GenericNode;
+    let (mut count, mut items)=(2, vec![1, 2]);
+    let server=memdom::render(move |root| hydrated_app(root, &mut count, &mut items));
+    let body=server.deep_clone();
+    let children=body.children();
+    assert_eq!(6, children.len());  // Both texts are kept in their own node.
+
+    let (mut count, mut items)=(2, vec![1, 2]);
+    let edom=EDOM::hydrate(body.clone(), move |root| hydrated_app(root, &mut count, &mut items));
+    assert!(edom.borrow().hydration_mismatches().is_empty());
+    assert!(children==body.children());
+    assert!(!body.child_elements()[1].dispatch_event("click", memdom::Event::new()));
+    assert_eq!(server.inner_html().replace("2<ul>", "3<ul>").replace("</ul>", "<li>3</li></ul>"),
+        body.inner_html());
+    assert_eq!(children[0], *body.child_elements()[0].into_generic_node());
+}
+
+#[test]
+fn test_hydrate_mismatch() {
+    let body: memdom::ElementNode=ElementNode::new("body");
+    let p: memdom::ElementNode=ElementNode::new("p");
+    p.set_text_content("ab");
+    body.append_child(&p);
+    body.append_child(&ElementNode::new("div"));
+    let edom=EDOM::hydrate(body.clone(), move |mut root| {
+        let mut p=root.element("p");
+        p.text("a");
+        p.text("b");
+        root.span(|span| span.text("x"));
+        root.text("y");
+    });
+    assert_eq!("<p>ab</p><span>x</span>y", body.inner_html());
+    assert_eq!(2, body.child_elements()[0].children().len());
+    assert_eq!(vec!["expected <span> at position 1, found <div>", "expected text \"x\" at position 0",
+        "expected text \"y\" at position 2"], edom.borrow().hydration_mismatches());
+}
+
+#[test]
+fn test_hydrate_removes_extra_nodes() {
+    let server=memdom::render(|mut root| {
+        root.ul(|ul| ul.for_each([1, 2, 3].iter(), |i| **i, "li", |i, li| li.text(i.to_string().as_str())));
+        root.text("stale");
+    });
+    let body=server.deep_clone();
+    let edom=EDOM::hydrate(body.clone(), |mut root| {
+        root.ul(|ul| ul.for_each([1, 2].iter(), |i| **i, "li", |i, li| li.text(i.to_string().as_str())));
+    });
+    assert_eq!("<ul><li>1</li><li>2</li></ul>", body.inner_html());
+    assert_eq!(vec!["expected nothing at position 1, found text", "expected nothing at position 2, found <li>"],
+        edom.borrow().hydration_mismatches());
+}
+
+#[test]
+fn test_invalid_names() {
+    let fields=vec![("title".to_string(), "h2"), ("first name".to_string(), "1h")];
+    let mut value="v".to_string();
+    let body: memdom::ElementNode=dom::ElementNode::new("body");
+    let edom=EDOM::render(body.clone(), move |mut root| {
+        if root.button("Change").clicked() {
+            value.push('!');
+        }
+        for (name, tag) in &fields {
+            root.element(tag.to_string()).attr(name.clone(), value.as_str());
+        }
+    });
+    assert_eq!("<button data-uid=\"1\">Change</button><h2 title=\"v\"></h2><div></div>", body.inner_html());
+    body.child_elements()[0].dispatch_event("click", memdom::Event::new());
+    assert_eq!("<button data-uid=\"1\">Change</button><h2 title=\"v!\"></h2><div></div>", body.inner_html());
+    assert_eq!(vec!["invalid tag name \"1h\" rendered as div", "invalid attribute name \"first name\" skipped"],
+        edom.borrow().invalid_names());
+}
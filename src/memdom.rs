@@ -0,0 +1,1028 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Wake, Waker};
+use std::time::Duration;
+
+use super::dom;
+use super::keyboard::KeyboardEvent;
+use super::pointer::PointerEvent;
+
+type FireEvent = Rc<RefCell<Box<dyn FnMut(u64, String, Event)>>>;
+
+enum NodeKind {
+    Element {
+        tag: String,
+        attributes: Vec<(String, String)>,
+        listeners: Vec<(String, FireEvent)>,
+    },
+    Text(String),
+}
+
+struct NodeData {
+    kind: NodeKind,
+    parent: Weak<RefCell<NodeData>>,
+    children: Vec<Node>,
+}
+
+thread_local! {
+    // The equivalent of `document.activeElement`, one per test thread.
+    static FOCUSED: RefCell<Weak<RefCell<NodeData>>> = const { RefCell::new(Weak::new()) };
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+    static QUEUED_EVENTS: RefCell<VecDeque<(FireEvent, u64, String, Event)>> =
+        RefCell::new(VecDeque::new());
+}
+
+/// Runs `f`, then fires the events that were dispatched synchronously meanwhile (like blur when
+/// rendering blurs the focused element), because they can't be handled while the render function
+/// is running. The wasm backend does the same with the events of the browser.
+pub(crate) fn run_and_fire_queued_events<R>(f: impl FnOnce()->R)->R {
+    if RUNNING.with(|r| r.replace(true)) {
+        return f();
+    }
+    let r=f();
+    while let Some((fire_event, uid, name, e))=QUEUED_EVENTS.with(|q| q.borrow_mut().pop_front()) {
+        (fire_event.borrow_mut())(uid, name, e);
+    }
+    RUNNING.with(|r| r.set(false));
+    r
+}
+
+/// Fires the event, or queues it if an event handler, a timer, a frame or a task is running.
+fn fire_or_queue_event(fire_event: FireEvent, uid: u64, name: String, e: Event) {
+    QUEUED_EVENTS.with(|q| q.borrow_mut().push_back((fire_event, uid, name, e)));
+    run_and_fire_queued_events(|| ());
+}
+
+/// A node of the in-memory tree. Cloning it clones the handle, not the node.
+#[derive(Clone)]
+pub struct Node(Rc<RefCell<NodeData>>);
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self)->bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Node {
+    fn new(kind: NodeKind)->Self {
+        Node(Rc::new(RefCell::new(NodeData { kind, parent: Weak::new(), children: Vec::new() })))
+    }
+
+    /// Returns the element if this node is an element.
+    pub fn as_element(&self)->Option<ElementNode> {
+        match self.0.borrow().kind {
+            NodeKind::Element { .. }=>Some(ElementNode { node: self.clone() }),
+            NodeKind::Text(_)=>None,
+        }
+    }
+
+    /// Returns the text node if this node is a text node.
+    pub fn as_text(&self)->Option<TextNode> {
+        match self.0.borrow().kind {
+            NodeKind::Text(_)=>Some(TextNode { node: self.clone() }),
+            NodeKind::Element { .. }=>None,
+        }
+    }
+
+    /// Parent element, if the node is attached.
+    pub fn parent(&self)->Option<ElementNode> {
+        self.0.borrow().parent.upgrade().map(|p| ElementNode { node: Node(p) })
+    }
+
+    /// Concatenated text of this node and all of its descendants.
+    pub fn text_content(&self)->String {
+        let mut s=String::new();
+        self.push_text_content(&mut s);
+        s
+    }
+
+    fn push_text_content(&self, s: &mut String) {
+        let data=self.0.borrow();
+        if let NodeKind::Text(t)=&data.kind {
+            s.push_str(t);
+        }
+        for child in &data.children {
+            child.push_text_content(s);
+        }
+    }
+
+    /// Appends the inner text of the node to `s`. `breaks` is the number of line breaks required
+    /// before the next text, which are only added if there's text before it.
+    fn push_inner_text(&self, s: &mut String, breaks: &mut usize) {
+        let data=self.0.borrow();
+        match &data.kind {
+            NodeKind::Text(t)=>push_inner_text_line(s, breaks, t),
+            NodeKind::Element { tag, .. } if tag=="br"=>push_inner_text_line(s, breaks, "\n"),
+            NodeKind::Element { tag, .. }=>{
+                let block=block_line_breaks(tag);
+                *breaks=(*breaks).max(block);
+                for child in &data.children {
+                    child.push_inner_text(s, breaks);
+                }
+                *breaks=(*breaks).max(block);
+            },
+        }
+    }
+
+    fn is_inclusive_descendant_of(&self, ancestor: &Node)->bool {
+        let mut node=Some(self.clone());
+        while let Some(n)=node {
+            if n == *ancestor {
+                return true;
+            }
+            node=n.0.borrow().parent.upgrade().map(Node);
+        }
+        false
+    }
+
+    fn index_in_parent(&self)->Option<usize> {
+        let parent=self.0.borrow().parent.upgrade()?;
+        let parent=parent.borrow();
+        parent.children.iter().position(|c| c == self)
+    }
+
+    fn detach(&self) {
+        let Some(parent)=self.0.borrow().parent.upgrade() else { return };
+        // Like in the DOM, removing the focused element or one of its ancestors moves the focus
+        // to the body without a blur event.
+        FOCUSED.with(|f| {
+            let focused=f.borrow().upgrade();
+            if focused.is_some_and(|n| Node(n).is_inclusive_descendant_of(self)) {
+                *f.borrow_mut()=Weak::new();
+            }
+        });
+        parent.borrow_mut().children.retain(|c| c != self);
+        self.0.borrow_mut().parent=Weak::new();
+    }
+
+    fn deep_clone(&self)->Node {
+        let data=self.0.borrow();
+        let kind=match &data.kind {
+            // Like `cloneNode(true)`, event listeners are not copied.
+            NodeKind::Element { tag, attributes, .. }=>NodeKind::Element {
+                tag: tag.clone(), attributes: attributes.clone(), listeners: Vec::new() },
+            NodeKind::Text(t)=>NodeKind::Text(t.clone()),
+        };
+        let r=Node::new(kind);
+        for child in &data.children {
+            let c=child.deep_clone();
+            c.0.borrow_mut().parent=Rc::downgrade(&r.0);
+            r.0.borrow_mut().children.push(c);
+        }
+        r
+    }
+
+    fn write_html(&self, s: &mut String) {
+        let data=self.0.borrow();
+        match &data.kind {
+            NodeKind::Text(t)=>crate::vdom::push_quoted_html(s, t),
+            NodeKind::Element { tag, attributes, .. }=>{
+                s.push('<');
+                s.push_str(tag);
+                for (name, value) in attributes {
+                    s.push(' ');
+                    s.push_str(name);
+                    s.push('=');
+                    crate::vdom::push_quoted_attr_value(s, value);
+                }
+                s.push('>');
+                if crate::vdom::is_void_element(tag) {
+                    return;
+                }
+                for child in &data.children {
+                    child.write_html(s);
+                }
+                s.push_str("</");
+                s.push_str(tag);
+                s.push('>');
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+        let mut s=String::new();
+        self.write_html(&mut s);
+        f.write_str(&s)
+    }
+}
+
+impl dom::GenericNode for Node {
+    type ElementNode = ElementNode;
+    type TextNode = TextNode;
+    fn into_text_node(self)->TextNode {
+        self.as_text().expect("Not a text node")
+    }
+    fn into_element_node(self)->ElementNode {
+        self.as_element().expect("Not an element node")
+    }
+    fn is_element(&self)->bool {
+        matches!(self.0.borrow().kind, NodeKind::Element { .. })
+    }
+}
+
+/// An element of the in-memory tree.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ElementNode {
+    node: Node,
+}
+
+impl ElementNode {
+    fn with_data<R>(&self, f: impl FnOnce(&str, &Vec<(String, String)>)->R)->R {
+        let data=self.node.0.borrow();
+        let NodeKind::Element { tag, attributes, .. }=&data.kind else { unreachable!() };
+        f(tag, attributes)
+    }
+
+    /// Tag name of the element.
+    pub fn tag(&self)->String {
+        self.with_data(|tag, _| tag.to_string())
+    }
+
+    /// Value of an attribute, `None` if it is not set.
+    pub fn attribute(&self, name: &str)->Option<String> {
+        self.with_data(|_, attributes|
+            attributes.iter().find(|a| a.0 == name).map(|a| a.1.clone()))
+    }
+
+    /// All attributes in the order they were first set.
+    pub fn attributes(&self)->Vec<(String, String)> {
+        self.with_data(|_, attributes| attributes.clone())
+    }
+
+    /// All child nodes, including text nodes.
+    pub fn children(&self)->Vec<Node> {
+        self.node.0.borrow().children.clone()
+    }
+
+    /// Child nodes that are elements.
+    pub fn child_elements(&self)->Vec<ElementNode> {
+        self.children().iter().filter_map(|c| c.as_element()).collect()
+    }
+
+    /// Parent element, if the element is attached.
+    pub fn parent(&self)->Option<ElementNode> {
+        self.node.parent()
+    }
+
+    /// Concatenated text of all descendant text nodes.
+    pub fn text_content(&self)->String {
+        self.node.text_content()
+    }
+
+    /// Names of the events that have listeners on this element.
+    pub fn listeners(&self)->Vec<String> {
+        let data=self.node.0.borrow();
+        let NodeKind::Element { listeners, .. }=&data.kind else { unreachable!() };
+        listeners.iter().map(|l| l.0.clone()).collect()
+    }
+
+    /// HTML of the element including its own tag.
+    pub fn outer_html(&self)->String {
+        let mut s=String::new();
+        self.node.write_html(&mut s);
+        s
+    }
+
+    /// Text of the element like `HTMLElement.innerText` without CSS: a `<br>` is a line break, and
+    /// block elements are on their own lines, paragraphs with an empty line around them.
+    pub fn inner_text(&self)->String {
+        let (mut s, mut breaks)=(String::new(), 0);
+        for child in self.children() {
+            child.push_inner_text(&mut s, &mut breaks);
+        }
+        s
+    }
+
+    /// HTML of the children of the element.
+    pub fn inner_html(&self)->String {
+        let mut s=String::new();
+        for child in self.children() {
+            child.write_html(&mut s);
+        }
+        s
+    }
+
+    /// Whether this element was the last one focused with [`dom::ElementNode::focus`].
+    pub fn has_focus(&self)->bool {
+        FOCUSED.with(|f| f.borrow().upgrade().is_some_and(|n| Rc::ptr_eq(&n, &self.node.0)))
+    }
+
+    /// Removes the focus from this element and dispatches a `blur` event if it was focused.
+    pub fn blur(&self) {
+        if self.has_focus() {
+            FOCUSED.with(|f| *f.borrow_mut()=Weak::new());
+            self.dispatch_event("blur", Event::new());
+        }
+    }
+
+    /// Dispatches an event on this element the same way the browser calls the listener
+    /// registered by the wasm event handler: the uid is read from the `data-uid` attribute.
+    /// The event doesn't bubble. Returns `false` if a listener called `prevent_default`,
+    /// like `EventTarget.dispatchEvent`. Events dispatched while rendering are fired after the
+    /// render.
+    pub fn dispatch_event(&self, name: &str, event: Event)->bool {
+        let fire_event={
+            let data=self.node.0.borrow();
+            let NodeKind::Element { listeners, .. }=&data.kind else { unreachable!() };
+            listeners.iter().find(|l| l.0 == name).map(|l| l.1.clone())
+        };
+        if let Some(fire_event)=fire_event {
+            let uid=self.attribute("data-uid").expect("No data-uid attribute")
+                .parse::<u64>().unwrap();
+            let default_prevented=event.default_prevented.clone();
+            fire_or_queue_event(fire_event, uid, name.to_string(), event);
+            !default_prevented.get()
+        } else {
+            true
+        }
+    }
+
+    fn add_listener(&self, name: String, fire_event: FireEvent) {
+        let mut data=self.node.0.borrow_mut();
+        let NodeKind::Element { listeners, .. }=&mut data.kind else { unreachable!() };
+        // The same listener is only registered once, like in `addEventListener`.
+        if !listeners.iter().any(|l| l.0 == name) {
+            listeners.push((name, fire_event));
+        }
+    }
+
+    fn insert(&self, child: &Node, pos: Option<usize>) {
+        child.detach();
+        child.0.borrow_mut().parent=Rc::downgrade(&self.node.0);
+        let mut data=self.node.0.borrow_mut();
+        let pos=pos.unwrap_or(data.children.len());
+        data.children.insert(pos, child.clone());
+    }
+
+    fn position_of(&self, child: &Node)->usize {
+        self.node.0.borrow().children.iter().position(|c| c == child)
+            .expect("The node is not a child of this element")
+    }
+}
+
+impl dom::ElementNode for ElementNode {
+    type TextNode = TextNode;
+    type Document = Document;
+    type GenericNode = Node;
+    type EventHandler = EventHandler;
+    type Event = Event;
+    type Executor = Executor;
+    fn replace_text_child(&self, new: &TextNode, old: &TextNode) {
+        let pos=self.position_of(&old.node);
+        old.node.detach();
+        self.insert(&new.node, Some(pos));
+    }
+    fn append_child(&self, child: &Self) {
+        self.insert(&child.node, None);
+    }
+    fn insert_child_before(&self, child: &Node, next_sibling: Option<&Node>) {
+        if next_sibling == Some(child) {
+            return;
+        }
+        child.detach();
+        let pos=next_sibling.map(|n| self.position_of(n));
+        self.insert(child, pos);
+    }
+    fn append_child_after(&self, child: &Self, prev_sibling: &Self) {
+        if child == prev_sibling {
+            return;
+        }
+        child.node.detach();
+        let pos=self.position_of(&prev_sibling.node) + 1;
+        self.insert(&child.node, Some(pos));
+    }
+    fn remove_child(&self, child: &Self) {
+        self.position_of(&child.node);
+        child.node.detach();
+    }
+    fn remove_child_node(&self, child: &Node) {
+        self.position_of(child);
+        child.detach();
+    }
+    fn prepend_child(&self, child: &Self) {
+        self.insert(&child.node, Some(0));
+    }
+    fn append_text_child(&self, child: &TextNode) {
+        self.insert(&child.node, None);
+    }
+    fn insert_text_child_before(&self, child: &TextNode, next_sibling: Option<&Node>) {
+        self.insert_child_before(&child.node, next_sibling);
+    }
+    fn set_attribute(&self, name: &str, value: &str) {
+        let mut data=self.node.0.borrow_mut();
+        let NodeKind::Element { attributes, .. }=&mut data.kind else { unreachable!() };
+        if let Some(a)=attributes.iter_mut().find(|a| a.0 == name) {
+            a.1=value.to_string();
+        } else {
+            attributes.push((name.to_string(), value.to_string()));
+        }
+    }
+    fn get_attribute(&self, name: &str)->String {
+        self.attribute(name).unwrap_or_default()
+    }
+    fn remove(&self) {
+        self.node.detach();
+    }
+    fn new(tag: impl Into<dom::Name>)->Self {
+        ElementNode { node: Node::new(NodeKind::Element {
+            tag: tag.into().into_owned(), attributes: Vec::new(), listeners: Vec::new() }) }
+    }
+    fn create_dnode_event_listener(&self, _f: Rc<RefCell<dyn FnMut(u64, String)>>, _uid: u64,
+            _name: &str) {
+    }
+    fn deep_clone(&self)->Self {
+        ElementNode { node: self.node.deep_clone() }
+    }
+    fn get_child_nodes(&self)->Vec<Node> {
+        self.children()
+    }
+    fn get_child_node(&self, i: u32)->Option<Node> {
+        self.node.0.borrow().children.get(i as usize).cloned()
+    }
+    fn set_text_content(&self, s: &str) {
+        for child in self.children() {
+            child.detach();
+        }
+        // Like in the DOM, setting an empty string leaves no text node behind.
+        if !s.is_empty() {
+            self.insert(&Node::new(NodeKind::Text(s.to_string())), None);
+        }
+    }
+    fn text_content(&self)->String {
+        self.node.text_content()
+    }
+    fn inner_text(&self)->String {
+        ElementNode::inner_text(self)
+    }
+    fn set_inner_text(&self, s: &str) {
+        for child in self.children() {
+            child.detach();
+        }
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.insert(&<ElementNode as dom::ElementNode>::new("br").node, None);
+            }
+            if !line.is_empty() {
+                self.insert(&Node::new(NodeKind::Text(line.to_string())), None);
+            }
+        }
+    }
+    fn inner_html(&self)->String {
+        ElementNode::inner_html(self)
+    }
+    fn set_inner_html(&self, html: &str) {
+        for child in self.children() {
+            child.detach();
+        }
+        parse_html(self, html);
+    }
+    fn into_generic_node(&self)->&Node {
+        &self.node
+    }
+    /// Like in the browser, the previously focused element gets a `blur` event and this element a
+    /// `focus` event synchronously.
+    fn focus(&self) {
+        if self.has_focus() {
+            return;
+        }
+        let previous=FOCUSED.with(|f| f.borrow().upgrade());
+        if let Some(previous)=previous {
+            ElementNode { node: Node(previous) }.blur();
+        }
+        FOCUSED.with(|f| *f.borrow_mut()=Rc::downgrade(&self.node.0));
+        self.dispatch_event("focus", Event::new());
+    }
+    fn has_focus(&self)->bool {
+        ElementNode::has_focus(self)
+    }
+    fn tag_name(&self)->String {
+        self.tag()
+    }
+}
+
+/// Parses an HTML fragment into the children of `parent`. Only simple HTML is supported: elements with
+/// quoted or unquoted attributes, text and the `&lt;`, `&gt;`, `&amp;`, `&quot;`, `&#39;`, `&nbsp;` and
+/// numeric escapes. Unmatched end tags close the current element.
+fn parse_html(parent: &ElementNode, html: &str) {
+    let mut stack=vec![parent.clone()];
+    let mut rest=html;
+    while !rest.is_empty() {
+        let current=stack.last().unwrap().clone();
+        if let Some(after)=rest.strip_prefix("</") {
+            if stack.len() > 1 {
+                stack.pop();
+            }
+            rest=after.find('>').map_or("", |i| &after[i + 1..]);
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let (tag, attributes, self_closing, after)=parse_start_tag(&rest[1..]);
+            let void=crate::vdom::is_void_element(&tag);
+            let element=ElementNode { node: Node::new(NodeKind::Element { tag, attributes, listeners: Vec::new() }) };
+            current.insert(&element.node, None);
+            if !void && !self_closing {
+                stack.push(element);
+            }
+            rest=after;
+        } else {
+            let end=rest[1..].find('<').map_or(rest.len(), |i| i + 1);
+            current.insert(&Node::new(NodeKind::Text(decode_entities(&rest[..end]))), None);
+            rest=&rest[end..];
+        }
+    }
+}
+
+/// Parses the part of a start tag after `<`. Returns the tag name, the attributes, whether it ends
+/// with `/>`, and the rest of the input after the tag.
+fn parse_start_tag(s: &str)->(String, Vec<(String, String)>, bool, &str) {
+    let name_end=s.find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(s.len());
+    let tag=s[..name_end].to_ascii_lowercase();
+    let mut attributes: Vec<(String, String)>=Vec::new();
+    let mut rest=&s[name_end..];
+    loop {
+        rest=rest.trim_start();
+        if let Some(after)=rest.strip_prefix("/>") {
+            return (tag, attributes, true, after);
+        }
+        if let Some(after)=rest.strip_prefix('>') {
+            return (tag, attributes, false, after);
+        }
+        if rest.is_empty() {
+            return (tag, attributes, false, rest);
+        }
+        let name_end=rest.find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len()).max(1);
+        let name=rest[..name_end].to_ascii_lowercase();
+        rest=rest[name_end..].trim_start();
+        let mut value=String::new();
+        if let Some(after)=rest.strip_prefix('=') {
+            let after=after.trim_start();
+            let (raw, after)=match after.chars().next() {
+                Some(quote @ ('"' | '\''))=>{
+                    let end=after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                },
+                _=>{
+                    let end=after.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value=decode_entities(raw);
+            rest=after;
+        }
+        if !attributes.iter().any(|a| a.0 == name) {
+            attributes.push((name, value));
+        }
+    }
+}
+
+/// Appends a text of the inner text after the line breaks required before it.
+fn push_inner_text_line(s: &mut String, breaks: &mut usize, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if !s.is_empty() {
+        s.extend(std::iter::repeat_n('\n', *breaks));
+    }
+    *breaks=0;
+    s.push_str(text);
+}
+
+/// Number of line breaks required around the inner text of an element with the default style of `tag`.
+fn block_line_breaks(tag: &str)->usize {
+    match tag.to_ascii_lowercase().as_str() {
+        "p"=>2,
+        "address" | "article" | "aside" | "blockquote" | "dd" | "div" | "dl" | "dt" | "fieldset" | "figcaption"
+            | "figure" | "footer" | "form" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "header" | "hr" | "li"
+            | "main" | "nav" | "ol" | "pre" | "section" | "table" | "tr" | "ul"=>1,
+        _=>0,
+    }
+}
+
+fn decode_entities(s: &str)->String {
+    let mut r=String::new();
+    let mut rest=s;
+    while let Some(i)=rest.find('&') {
+        r.push_str(&rest[..i]);
+        rest=&rest[i..];
+        let entity=rest.find(';').map(|end| (&rest[1..end], end));
+        let decoded=entity.and_then(|(name, end)| {
+            let c=match name {
+                "lt"=>Some('<'),
+                "gt"=>Some('>'),
+                "amp"=>Some('&'),
+                "quot"=>Some('"'),
+                "nbsp"=>Some('\u{a0}'),
+                _=>name.strip_prefix('#').and_then(|n| match n.strip_prefix(['x', 'X']) {
+                    Some(hex)=>u32::from_str_radix(hex, 16).ok(),
+                    None=>n.parse().ok(),
+                }).and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end))=>{
+                r.push(c);
+                rest=&rest[end + 1..];
+            },
+            None=>{
+                r.push('&');
+                rest=&rest[1..];
+            }
+        }
+    }
+    r.push_str(rest);
+    r
+}
+
+/// A text node of the in-memory tree.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextNode {
+    node: Node,
+}
+
+impl TextNode {
+    /// The text data of the node.
+    pub fn text(&self)->String {
+        self.node.text_content()
+    }
+    /// Position of the node between the children of its parent.
+    pub fn index_in_parent(&self)->Option<usize> {
+        self.node.index_in_parent()
+    }
+}
+
+impl dom::TextNode for TextNode {
+    fn new(text: &str)->Self {
+        TextNode { node: Node::new(NodeKind::Text(text.to_string())) }
+    }
+    fn text_data(&self)->String {
+        self.text()
+    }
+}
+
+/// Creates nodes of the in-memory tree.
+pub struct Document {
+}
+
+impl dom::Document for Document {
+    type TextNode = TextNode;
+    type ElementNode = ElementNode;
+    fn create_text_node(&self, text: &str)->TextNode {
+        dom::TextNode::new(text)
+    }
+    fn new()->Self {
+        Document {}
+    }
+    fn create_element(&self, tag: impl Into<dom::Name>)->ElementNode {
+        dom::ElementNode::new(tag)
+    }
+    fn active_element(&self)->Option<ElementNode> {
+        FOCUSED.with(|f| f.borrow().upgrade()).map(|n| ElementNode { node: Node(n) })
+    }
+    fn log_1(_s: &str) {
+    }
+    fn log_2(_s: &str, _s2: &str) {
+    }
+}
+
+/// An event that can be dispatched with [`ElementNode::dispatch_event`].
+#[derive(Clone, Default)]
+pub struct Event {
+    default_prevented: Rc<Cell<bool>>,
+    keyboard: Option<KeyboardEvent>,
+    pointer: Option<PointerEvent>,
+}
+
+impl Event {
+    /// Creates an event with no data.
+    pub fn new()->Self {
+        Self::default()
+    }
+    /// Creates a keyboard event with the given key value.
+    pub fn with_key(key: &str)->Self {
+        Self::with_keyboard(KeyboardEvent::new(key.into()))
+    }
+    /// Creates a keyboard event.
+    pub fn with_keyboard(keyboard: KeyboardEvent)->Self {
+        Event { keyboard: Some(keyboard), ..Self::default() }
+    }
+    /// Creates a mouse, pointer or wheel event.
+    pub fn with_pointer(pointer: PointerEvent)->Self {
+        Event { pointer: Some(pointer), ..Self::default() }
+    }
+    /// The key value of a keyboard event.
+    pub fn key(&self)->Option<String> {
+        self.keyboard.as_ref().map(|k| k.key.to_string())
+    }
+    /// Whether `prevent_default` was called on the event.
+    pub fn default_prevented(&self)->bool {
+        self.default_prevented.get()
+    }
+}
+
+impl dom::Event for Event {
+    fn prevent_default(&self) {
+        self.default_prevented.set(true);
+    }
+    fn keyboard(&self)->Option<KeyboardEvent> {
+        self.keyboard.clone()
+    }
+    fn pointer(&self)->Option<PointerEvent> {
+        self.pointer.clone()
+    }
+}
+
+/// Registers listeners on [`ElementNode`]s that call back into [`crate::EDOM`] when
+/// [`ElementNode::dispatch_event`] is called, and timers that fire when its [`Clock`] is advanced.
+pub struct EventHandler {
+    fire_event: FireEvent,
+    clock: Clock,
+}
+
+impl EventHandler {
+    /// The fake clock that drives the timers.
+    pub fn clock(&self)->&Clock {
+        &self.clock
+    }
+}
+
+impl dom::EventHandler for EventHandler {
+    type ElementNode = ElementNode;
+    type Event = Event;
+    type Timer = Timer;
+    fn new(fire_event: FireEvent)->Self {
+        Self { fire_event, clock: Clock::default() }
+    }
+    fn create_event_listener(&self, e: &ElementNode, name: String) {
+        e.add_listener(name, self.fire_event.clone());
+    }
+    fn create_timer(&self, uid: u64, name: String, interval: Duration, repeat: bool)->Timer {
+        self.clock.add(uid, name, interval, repeat, self.fire_event.clone())
+    }
+    fn now(&self)->Duration {
+        self.clock.now()
+    }
+}
+
+struct TimerData {
+    due: u128,
+    interval: u128,
+    repeat: bool,
+    uid: u64,
+    name: String,
+    fire_event: FireEvent,
+}
+
+/// A timer created by [`EventHandler`], cancelled when dropped.
+pub struct Timer(#[allow(dead_code)] Rc<RefCell<TimerData>>);
+
+/// Time that only passes when [`Clock::advance`] is called.
+#[derive(Clone, Default)]
+pub struct Clock {
+    now: Rc<Cell<u128>>,
+    timers: Rc<RefCell<Vec<Weak<RefCell<TimerData>>>>>,
+}
+
+impl Clock {
+    fn add(&self, uid: u64, name: String, interval: Duration, repeat: bool, fire_event: FireEvent)
+           ->Timer {
+        let interval=interval.as_millis();
+        let timer=Rc::new(RefCell::new(TimerData {
+            due: self.now.get() + interval, interval, repeat, uid, name, fire_event }));
+        self.timers.borrow_mut().push(Rc::downgrade(&timer));
+        Timer(timer)
+    }
+
+    /// Milliseconds elapsed since the clock was created.
+    pub fn now(&self)->Duration {
+        Duration::from_millis(self.now.get() as u64)
+    }
+
+    /// Number of timers that weren't cancelled and will fire again.
+    pub fn pending(&self)->usize {
+        self.timers.borrow_mut().retain(|t| t.strong_count() > 0);
+        self.timers.borrow().len()
+    }
+
+    /// Moves the time forward, firing the timers that become due in the order of their due time.
+    pub fn advance(&self, d: Duration) {
+        let target=self.now.get() + d.as_millis();
+        loop {
+            self.timers.borrow_mut().retain(|t| t.strong_count() > 0);
+            // The first timer with the earliest due time, so timers with equal due times
+            // fire in the order they were created.
+            let next=self.timers.borrow().iter().enumerate()
+                .filter_map(|(i, t)| t.upgrade().map(|t| { let due=t.borrow().due; (due, i, t) }))
+                .filter(|t| t.0 <= target)
+                .min_by_key(|t| (t.0, t.1));
+            let Some((due, i, timer))=next else { break };
+            self.now.set(due);
+            let (uid, name, fire_event)={
+                let mut t=timer.borrow_mut();
+                if t.repeat {
+                    t.due+=t.interval.max(1);
+                } else {
+                    self.timers.borrow_mut().remove(i);
+                }
+                (t.uid, t.name.clone(), t.fire_event.clone())
+            };
+            // The timer may be dropped (cancelled) by rendering.
+            drop(timer);
+            fire_or_queue_event(fire_event, uid, name, Event::new());
+        }
+        self.now.set(target);
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.lock().unwrap().push(self.id);
+    }
+}
+
+/// Keeps the spawned futures until [`Executor::run_until_stalled`] is called, so tests can
+/// decide when they make progress.
+#[derive(Clone, Default)]
+pub struct Executor {
+    tasks: Rc<RefCell<Vec<Option<dom::LocalFuture>>>>,
+    ready: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Executor {
+    /// Polls the woken futures until none of them can make progress.
+    /// Returns the number of futures that are still pending.
+    pub fn run_until_stalled(&self)->usize {
+        run_and_fire_queued_events(|| self.poll_ready_tasks());
+        self.tasks.borrow().iter().filter(|t| t.is_some()).count()
+    }
+
+    fn poll_ready_tasks(&self) {
+        loop {
+            let ready=std::mem::take(&mut *self.ready.lock().unwrap());
+            if ready.is_empty() {
+                break;
+            }
+            for id in ready {
+                // The future is taken out while polling, so it can spawn new futures.
+                let Some(mut future)=self.tasks.borrow_mut()[id].take() else { continue };
+                let waker=Waker::from(Arc::new(TaskWaker { id, ready: self.ready.clone() }));
+                if future.as_mut().poll(&mut Context::from_waker(&waker)).is_pending() {
+                    self.tasks.borrow_mut()[id]=Some(future);
+                }
+            }
+        }
+    }
+}
+
+impl dom::Executor for Executor {
+    fn new()->Self {
+        Self::default()
+    }
+    fn spawn_local(&self, future: dom::LocalFuture) {
+        let mut tasks=self.tasks.borrow_mut();
+        self.ready.lock().unwrap().push(tasks.len());
+        tasks.push(Some(future));
+    }
+}
+
+type FrameCallback = Box<dyn FnOnce()>;
+
+/// Keeps the callbacks of requested frames until [`FrameScheduler::run_frame`] is called.
+#[derive(Clone, Default)]
+pub struct FrameScheduler {
+    callbacks: Rc<RefCell<Vec<FrameCallback>>>,
+}
+
+impl FrameScheduler {
+    /// Creates a scheduler without requested frames.
+    pub fn new()->Self {
+        Self::default()
+    }
+    /// Number of callbacks waiting for the next frame.
+    pub fn requested(&self)->usize {
+        self.callbacks.borrow().len()
+    }
+    /// Calls the callbacks that were requested before this call. Returns their number.
+    pub fn run_frame(&self)->usize {
+        let callbacks=std::mem::take(&mut *self.callbacks.borrow_mut());
+        let n=callbacks.len();
+        run_and_fire_queued_events(|| {
+            for callback in callbacks {
+                callback();
+            }
+        });
+        n
+    }
+}
+
+impl dom::Scheduler for FrameScheduler {
+    fn request_frame(&self, callback: Box<dyn FnOnce()>) {
+        self.callbacks.borrow_mut().push(callback);
+    }
+}
+
+/// Renders into a new in-memory `body` element and returns it.
+pub fn render<F>(f: F)->ElementNode where F: FnMut(super::Visitor<ElementNode>) + 'static {
+    let body: ElementNode=dom::ElementNode::new("body");
+    run_and_fire_queued_events(|| super::EDOM::render(body.clone(), f));
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::{ElementNode as _, Document as _, GenericNode as _};
+
+    #[test]
+    fn tree_operations() {
+        let doc=Document::new();
+        let ul=doc.create_element("ul");
+        let a=doc.create_element("li");
+        let b=doc.create_element("li");
+        let c=doc.create_element("li");
+        ul.append_child(&a);
+        ul.append_child(&b);
+        ul.prepend_child(&c);
+        assert_eq!(vec![c.clone(), a.clone(), b.clone()], ul.child_elements());
+        ul.append_child_after(&c, &b);
+        assert_eq!(vec![a.clone(), b.clone(), c.clone()], ul.child_elements());
+        ul.insert_child_before(b.into_generic_node(), Some(a.into_generic_node()));
+        assert_eq!(vec![b.clone(), a.clone(), c.clone()], ul.child_elements());
+        a.remove();
+        ul.remove_child(&c);
+        assert_eq!(vec![b.clone()], ul.child_elements());
+        assert!(a.parent().is_none());
+        assert!(b.parent() == Some(ul.clone()));
+    }
+
+    #[test]
+    fn text_and_clone() {
+        let doc=Document::new();
+        let div=doc.create_element("div");
+        div.set_attribute("class", "a\"b");
+        div.set_text_content("x<y");
+        let t=doc.create_text_node("!");
+        div.append_text_child(&t);
+        assert_eq!("<div class=\"a&quot;b\">x&lt;y!</div>", div.outer_html());
+        let copy=div.deep_clone();
+        div.replace_text_child(&doc.create_text_node("?"), &t);
+        assert_eq!("x<y?", div.text_content());
+        assert_eq!("x<y!", copy.text_content());
+        assert_eq!("!", copy.get_child_node(1).unwrap().into_text_node().text());
+        assert!(copy.parent().is_none());
+    }
+
+    #[test]
+    fn render_and_dispatch() {
+        let mut v: Vec<u64>=vec![1, 2, 3];
+        let body=render(move |mut root| {
+            if root.button("Reverse").clicked() {
+                v.reverse();
+            }
+            root.element("ul").for_each(v.iter_mut(), |e| **e, "li", |e, li| {
+                li.text(e.to_string().as_str());
+            });
+        });
+        assert_eq!("<button data-uid=\"1\">Reverse</button><ul><li>1</li><li>2</li><li>3</li></ul>",
+            body.inner_html());
+        let event=Event::new();
+        assert!(!body.child_elements()[0].dispatch_event("click", event.clone()));
+        assert!(event.default_prevented());
+        assert_eq!("<li>3</li><li>2</li><li>1</li>", body.child_elements()[1].inner_html());
+    }
+
+    #[test]
+    fn blur_during_render() {
+        let mut save=false;
+        let mut saved=0;
+        let app=crate::testing::TestApp::new(move |mut root| {
+            if root.button("Save").clicked() {
+                save=true;
+            }
+            let mut input=root.element("input");
+            if input.lost_focus() {
+                saved+=1;
+            }
+            if save {
+                // Like pressing Enter in an edited field: the blur event is handled after this render.
+                input.get_dnode().blur();
+                save=false;
+            }
+            root.text(saved.to_string().as_str());
+        });
+        let input=app.find_by_tag("input").unwrap();
+        app.focus(&input);
+        app.click(&app.find_by_text("Save").unwrap());
+        assert!(!app.has_focus(&input));
+        assert_eq!("1", app.body().children()[2].text_content());
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::dom;
+use super::visitor::Visitor;
+
+impl<'d, 'e, 'f, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Renders a `tag` element with `fcb` like [`Visitor::element_with_children`], but doesn't call
+    /// `fcb` if `key` is the same as in the previous render: the element and its DOM are kept as they are.
+    /// ```
+    /// use std::{cell::Cell, rc::Rc};
+    /// let renders=Rc::new(Cell::new(0));
+    /// let counted=renders.clone();
+    /// let mut count=0;
+    /// let app=edom::testing::TestApp::new(move |mut root| {
+    ///     root.memo((), "ul", |ul| {
+    ///         counted.set(counted.get() + 1);
+    ///         ul.for_each(0..10000, |i| *i, "li", |i, li| li.text(i.to_string().as_str()));
+    ///     });
+    ///     if root.button("+").clicked() {
+    ///         count+=1;
+    ///     }
+    ///     root.text(count.to_string().as_str());
+    /// });
+    /// app.click(&app.find_by_text("+").unwrap());
+    /// assert!(app.html().ends_with("+</button>1"));
+    /// assert_eq!(1, renders.get());
+    /// ```
+    /// The key has to change whenever the output of `fcb` would, so it usually contains the rendered
+    /// data or a version number of it. Animations and tasks inside the element are not updated while
+    /// the key is the same. `fcb` is still called when an event or timer of an element inside it
+    /// is handled, so the handlers run.
+    pub fn memo<K, FCB>(&'f mut self, key: K, tag: impl Into<dom::Name>, mut fcb: FCB)
+            where K: Hash, FCB: FnMut(&mut Visitor<EN>) {
+        let mut hasher=DefaultHasher::new();
+        key.hash(&mut hasher);
+        let key=Some(hasher.finish());
+        let mut it=self.element(tag);
+        let event_inside=it.edom.firing_event.as_ref().is_some_and(|e| it.element.contains_uid(e.0));
+        if !it.edom.create && it.element.extras.as_ref().and_then(|x| x.memo_key) == key && !event_inside {
+            return;
+        }
+        it.element.extras_mut().memo_key=key;
+        fcb(&mut it);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::testing::TestApp;
+
+    #[test]
+    fn skips_unchanged_subtrees() {
+        let renders=Rc::new(Cell::new(0));
+        let counted=renders.clone();
+        let mut items=vec![(1, false), (2, false)];
+        let mut version=0;
+        let mut clicks=0;
+        let app=TestApp::new(move |mut root| {
+            root.memo(version, "ul", |ul| {
+                counted.set(counted.get() + 1);
+                ul.for_each(items.iter_mut(), |item| item.0, "li", |item, li| {
+                    if li.button(item.0.to_string().as_str()).clicked() {
+                        item.1=!item.1;
+                        version+=1;
+                    }
+                    li.text(if item.1 { "done" } else { "" });
+                });
+            });
+            if root.button("add").clicked() {
+                items.push((items.len() + 1, false));
+                version+=1;
+            }
+            if root.button("click").clicked() {
+                clicks+=1;
+            }
+            root.text(clicks.to_string().as_str());
+        });
+        let list=|| app.body().child_elements()[0].text_content();
+        assert_eq!(1, renders.get());
+        // The siblings after a skipped element are updated.
+        app.click(&app.find_by_text("click").unwrap());
+        assert!(app.html().ends_with(">click</button>1"));
+        assert_eq!(1, renders.get());
+        // An event inside the element runs its handler and the new key renders it again.
+        app.click(&app.find_by_text("2").unwrap());
+        assert_eq!("12done", list());
+        assert_eq!(3, renders.get());
+        app.click(&app.find_by_text("add").unwrap());
+        assert_eq!("12done3", list());
+        assert_eq!(4, renders.get());
+        app.click(&app.find_by_text("click").unwrap());
+        assert!(app.html().ends_with(">click</button>2"));
+        assert_eq!(4, renders.get());
+    }
+}
@@ -0,0 +1,322 @@
+use std::time::Duration;
+
+use super::dom;
+use super::visitor::Visitor;
+
+/// Values that can be animated by [`Tweened`].
+pub trait Interpolate: Clone {
+    /// The value at `t` between `self` (`t=0`) and `to` (`t=1`).
+    /// `t` can be outside of `0..=1` for easing functions that overshoot.
+    fn interpolate(&self, to: &Self, t: f64)->Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, to: &Self, t: f64)->Self {
+        self + (to - self) * t
+    }
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, to: &Self, t: f64)->Self {
+        self + (to - self) * t as f32
+    }
+}
+
+/// Points are interpolated by coordinates.
+impl Interpolate for (f64, f64) {
+    fn interpolate(&self, to: &Self, t: f64)->Self {
+        (self.0.interpolate(&to.0, t), self.1.interpolate(&to.1, t))
+    }
+}
+
+impl<const N: usize> Interpolate for [f64; N] {
+    fn interpolate(&self, to: &Self, t: f64)->Self {
+        let mut r=*self;
+        for (r, to) in r.iter_mut().zip(to) {
+            *r=r.interpolate(to, t);
+        }
+        r
+    }
+}
+
+/// An RGBA color that can be animated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    /// Red component (0-255).
+    pub r: u8,
+    /// Green component (0-255).
+    pub g: u8,
+    /// Blue component (0-255).
+    pub b: u8,
+    /// Opacity (0.0-1.0).
+    pub a: f64,
+}
+
+impl Color {
+    /// An opaque color.
+    pub fn rgb(r: u8, g: u8, b: u8)->Self {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    /// The color in CSS `rgba()` notation.
+    pub fn to_css(&self)->String {
+        format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, self.a)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, to: &Self, t: f64)->Self {
+        let channel=|from: u8, to: u8| (from as f64).interpolate(&(to as f64), t).round().clamp(0.0, 255.0) as u8;
+        Color {
+            r: channel(self.r, to.r),
+            g: channel(self.g, to.g),
+            b: channel(self.b, to.b),
+            a: self.a.interpolate(&to.a, t).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Easing functions that map the elapsed fraction of an animation (0-1) to its progress.
+pub mod easing {
+    /// Constant speed.
+    pub fn linear(t: f64)->f64 {
+        t
+    }
+    /// Accelerating from zero speed.
+    pub fn quad_in(t: f64)->f64 {
+        t * t
+    }
+    /// Decelerating to zero speed.
+    pub fn quad_out(t: f64)->f64 {
+        t * (2.0 - t)
+    }
+    /// Accelerating until halfway, then decelerating.
+    pub fn quad_in_out(t: f64)->f64 {
+        if t < 0.5 { 2.0 * t * t } else { -1.0 + (4.0 - 2.0 * t) * t }
+    }
+    /// Accelerating from zero speed.
+    pub fn cubic_in(t: f64)->f64 {
+        t * t * t
+    }
+    /// Decelerating to zero speed.
+    pub fn cubic_out(t: f64)->f64 {
+        let f=t - 1.0;
+        f * f * f + 1.0
+    }
+    /// Accelerating until halfway, then decelerating.
+    pub fn cubic_in_out(t: f64)->f64 {
+        if t < 0.5 { 4.0 * t * t * t } else { (t - 1.0) * (2.0 * t - 2.0) * (2.0 * t - 2.0) + 1.0 }
+    }
+}
+
+/// A value that moves smoothly to a new target when it's set, like Svelte's `tweened` store:
+/// ```
+/// use std::time::Duration;
+/// use edom::motion::{easing, Tweened};
+/// let mut progress=Tweened::new(0.0).duration(Duration::from_millis(160)).easing(easing::linear);
+/// let app=edom::testing::TestApp::new(move |mut root| {
+///     if root.button("Done").clicked() {
+///         progress.set(1.0);
+///     }
+///     let value=root.animate(&mut progress);
+///     root.element("progress").attr("value", value.to_string().as_str());
+/// });
+/// app.click(&app.find_by_text("Done").unwrap());
+/// app.advance_time(Duration::from_millis(80));
+/// assert_eq!(Some("0.5"), app.find_by_tag("progress").unwrap().attribute("value"));
+/// app.advance_time(Duration::from_millis(80));
+/// assert_eq!(Some("1"), app.find_by_tag("progress").unwrap().attribute("value"));
+/// ```
+pub struct Tweened<T> where T: Interpolate {
+    from: T,
+    to: T,
+    current: T,
+    start: Option<Duration>,  // None until the first update after the target was set.
+    animating: bool,
+    duration: Duration,
+    easing: fn(f64)->f64,
+}
+
+impl<T> Tweened<T> where T: Interpolate {
+    /// A value that is not moving, with a duration of 400ms and [`easing::cubic_out`] easing.
+    pub fn new(value: T)->Self {
+        Tweened { from: value.clone(), to: value.clone(), current: value, start: None, animating: false,
+            duration: Duration::from_millis(400), easing: easing::cubic_out }
+    }
+
+    /// Sets the duration of the following animations.
+    pub fn duration(mut self, duration: Duration)->Self {
+        self.duration=duration;
+        self
+    }
+
+    /// Sets the easing function of the following animations.
+    pub fn easing(mut self, easing: fn(f64)->f64)->Self {
+        self.easing=easing;
+        self
+    }
+
+    /// Starts moving from the current value to `target`. The animation starts at the next
+    /// [`Tweened::update`].
+    pub fn set(&mut self, target: T) {
+        self.from=self.current.clone();
+        self.to=target;
+        self.start=None;
+        self.animating=true;
+    }
+
+    /// Jumps to `value` without animation.
+    pub fn set_immediately(&mut self, value: T) {
+        self.from=value.clone();
+        self.to=value.clone();
+        self.current=value;
+        self.animating=false;
+    }
+
+    /// The value computed by the last [`Tweened::update`].
+    pub fn value(&self)->&T {
+        &self.current
+    }
+
+    /// The value that is being moved to.
+    pub fn target(&self)->&T {
+        &self.to
+    }
+
+    /// Whether the target is not reached yet.
+    pub fn is_animating(&self)->bool {
+        self.animating
+    }
+
+    /// Computes the value at time `now` (of any clock that is used consistently) and returns it.
+    pub fn update(&mut self, now: Duration)->&T {
+        if !self.animating {
+            return &self.current;
+        }
+        let start=*self.start.get_or_insert(now);
+        let elapsed=now.saturating_sub(start);
+        if elapsed >= self.duration {
+            self.current=self.to.clone();
+            self.animating=false;
+        } else {
+            let t=elapsed.as_secs_f64() / self.duration.as_secs_f64();
+            self.current=self.from.interpolate(&self.to, (self.easing)(t));
+        }
+        &self.current
+    }
+}
+
+impl<'d, 'e, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Updates `tweened` with the clock of the event handler and returns its current value.
+    /// While it's animating, the application is re-rendered in every frame.
+    pub fn animate<T>(&mut self, tweened: &mut Tweened<T>)->T where T: Interpolate {
+        let now=dom::EventHandler::now(&self.edom.event_handler);
+        let value=tweened.update(now).clone();
+        if tweened.is_animating() {
+            self.edom.request_animation_frame();
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64)->Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn interpolation() {
+        let mut t=Tweened::new(10.0).duration(ms(100)).easing(easing::linear);
+        t.set(20.0);
+        assert!(t.is_animating());
+        assert_eq!(10.0, *t.update(ms(1000)));
+        assert_eq!(12.5, *t.update(ms(1025)));
+        t.set(0.0);
+        assert_eq!(12.5, *t.update(ms(1050)));
+        assert_eq!(6.25, *t.update(ms(1100)));
+        assert_eq!(0.0, *t.update(ms(1150)));
+        assert!(!t.is_animating());
+        assert_eq!(0.0, *t.update(ms(2000)));
+
+        let mut c=Tweened::new(Color::rgb(0, 0, 0)).duration(ms(10)).easing(easing::linear);
+        c.set(Color { r: 255, g: 100, b: 0, a: 0.0 });
+        c.update(ms(0));
+        assert_eq!("rgba(128, 50, 0, 0.5)", c.update(ms(5)).to_css());
+        let mut p=Tweened::new((0.0, 0.0)).duration(ms(10)).easing(easing::quad_in);
+        p.set((10.0, -10.0));
+        p.update(ms(0));
+        assert_eq!((2.5, -2.5), *p.update(ms(5)));
+    }
+
+    #[test]
+    fn easing_functions() {
+        let all: [fn(f64)->f64; 7]=[easing::linear, easing::quad_in, easing::quad_out,
+            easing::quad_in_out, easing::cubic_in, easing::cubic_out, easing::cubic_in_out];
+        for f in all {
+            assert_eq!(0.0, f(0.0));
+            assert_eq!(1.0, f(1.0));
+        }
+        assert_eq!(0.5, easing::cubic_in_out(0.5));
+    }
+
+    #[test]
+    fn rerenders_until_done() {
+        let renders=std::rc::Rc::new(std::cell::Cell::new(0));
+        let moved_renders=renders.clone();
+        let mut width=Tweened::new(0.0).duration(ms(160)).easing(easing::linear);
+        let app=crate::testing::TestApp::new(move |mut root| {
+            moved_renders.set(moved_renders.get() + 1);
+            if root.button("Grow").clicked() {
+                width.set(100.0);
+            }
+            let w=root.animate(&mut width);
+            root.div(|div| { div.attr("style", format!("width: {}px", w).as_str()); });
+        });
+        app.click(&app.find_by_text("Grow").unwrap());
+        let style=|| app.find_by_tag("div").unwrap().attribute("style").unwrap().to_string();
+        assert_eq!("width: 0px", style());
+        app.advance_time(ms(80));
+        assert_eq!("width: 50px", style());
+        app.advance_time(ms(1000));
+        assert_eq!("width: 100px", style());
+        let n=renders.get();
+        app.advance_time(ms(1000));
+        assert_eq!(n, renders.get());
+        assert_eq!(0, app.pending_timers());
+    }
+
+    #[test]
+    fn events_during_animation_are_batched() {
+        let renders=std::rc::Rc::new(std::cell::Cell::new(0));
+        let moved_renders=renders.clone();
+        let mut width=Tweened::new(0.0).duration(ms(160)).easing(easing::linear);
+        let mut clicks=0;
+        let app=crate::testing::TestApp::new(move |mut root| {
+            moved_renders.set(moved_renders.get() + 1);
+            if root.button("Grow").clicked() {
+                width.set(100.0);
+            }
+            if root.button("Click").clicked() {
+                clicks+=1;
+            }
+            let w=root.animate(&mut width);
+            root.text(format!("{} {}", w, clicks).as_str());
+        });
+        let frames=app.batch_renders();
+        app.click(&app.find_by_text("Grow").unwrap());
+        assert_eq!(1, frames.run_frame());
+        assert_eq!(3, renders.get());
+        // Only the handler passes run for the events, the animation is rendered in the same frame.
+        for _ in 0..5 {
+            app.click(&app.find_by_text("Click").unwrap());
+        }
+        assert_eq!(8, renders.get());
+        assert_eq!(1, frames.requested());
+        assert_eq!(1, frames.run_frame());
+        assert_eq!(9, renders.get());
+        assert!(app.html().ends_with("0 5"));
+    }
+}
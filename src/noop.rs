@@ -10,16 +10,35 @@ pub struct EventHandler {
 impl dom::EventHandler for EventHandler {
     type ElementNode=ElementNode;
     type Event=Event;
+    type Timer=();
     fn new(_fire_event: Rc<RefCell<Box<dyn FnMut(u64, String, Event)>>>)->Self {
         Self {}
     }
     fn create_event_listener(&self, _e: &Self::ElementNode, _name: String) {
     }
+    fn create_timer(&self, _uid: u64, _name: String, _interval: std::time::Duration, _repeat: bool) {
+    }
+    fn now(&self)->std::time::Duration {
+        std::time::Duration::ZERO
+    }
 }
 
+#[derive(Clone, PartialEq)]
 pub struct Node {
 }
 
+/// Drops the spawned futures, so they stay pending.
+pub struct Executor {
+}
+
+impl dom::Executor for Executor {
+    fn new()->Self {
+        Self {}
+    }
+    fn spawn_local(&self, _future: dom::LocalFuture) {
+    }
+}
+
 
 impl dom::GenericNode for Node {
     type ElementNode = ElementNode;
@@ -28,13 +47,22 @@ impl dom::GenericNode for Node {
         TextNode { text: "hello".to_string()}
     }
     fn into_element_node(self)->Self::ElementNode {
-        ElementNode { tag: "hello", generic_node: Node {  }}
+        ElementNode { tag: "hello".into(), generic_node: Node {  }}
+    }
+    fn is_element(&self)->bool {
+        true
     }
 }
 
 pub struct Event {}
 impl dom::Event for Event {
     fn prevent_default(&self) {}
+    fn keyboard(&self)->Option<crate::keyboard::KeyboardEvent> {
+        None
+    }
+    fn pointer(&self)->Option<crate::pointer::PointerEvent> {
+        None
+    }
 }
 
 
@@ -45,14 +73,28 @@ impl  dom::ElementNode for ElementNode {
     type GenericNode=Node;
     type EventHandler=EventHandler;
     type Event=Event;
-    fn new(tag: &'static str)->Self {
-        Self { tag, generic_node: Node {  }}
+    type Executor=Executor;
+    fn new(tag: impl Into<dom::Name>)->Self {
+        Self { tag: tag.into(), generic_node: Node {  }}
     } 
-    fn create_dnode_event_listener(&self, _f : Rc<RefCell<dyn FnMut(u64, &'static str)>>, _uid:u64,
-            _name:&'static str) {
+    fn create_dnode_event_listener(&self, _f : Rc<RefCell<dyn FnMut(u64, String)>>, _uid:u64,
+            _name:&str) {
     }
     fn set_text_content(&self, _s:&str) {
     }
+    fn text_content(&self)->String {
+        String::new()
+    }
+    fn inner_text(&self)->String {
+        String::new()
+    }
+    fn set_inner_text(&self, _s:&str) {
+    }
+    fn inner_html(&self)->String {
+        String::new()
+    }
+    fn set_inner_html(&self, _html: &str) {
+    }
     fn replace_text_child(&self, _new: &TextNode, _old: &TextNode) {
     }
     fn append_child(&self, _child: &ElementNode) {
@@ -66,11 +108,16 @@ impl  dom::ElementNode for ElementNode {
     }
     fn remove_child(&self, child: &Self) {
     }
+    fn remove_child_node(&self, _child: &Node) {
+    }
     fn prepend_child(&self, _child: &ElementNode) {
 
     }
     fn append_text_child(&self, _child: &TextNode) {
 
+    }
+    fn insert_text_child_before(&self, _child: &TextNode, _next_sibling: Option<&Node>) {
+
     }
     fn set_attribute(&self, _name: &str, _value: &str) {
 
@@ -82,7 +129,7 @@ impl  dom::ElementNode for ElementNode {
     fn remove(&self) {
     }
     fn deep_clone(&self)->Self {
-        ElementNode { tag: self.tag, generic_node: Node {  } }
+        ElementNode { tag: self.tag.clone(), generic_node: Node {  } }
     }
     fn get_child_nodes(&self)->Vec<Self::GenericNode> {
         Vec::new()
@@ -95,13 +142,20 @@ impl  dom::ElementNode for ElementNode {
     }
     fn focus(&self) {
     }
+    fn has_focus(&self)->bool {
+        false
+    }
+    fn tag_name(&self)->String {
+        self.tag.to_string()
+    }
 
 }
 
 
+#[derive(Clone)]
 pub struct ElementNode {
     pub generic_node: Node,
-    pub tag: &'static str
+    pub tag: dom::Name
 }
 
 pub struct Document {
@@ -112,11 +166,14 @@ impl dom::Document for Document {
     fn create_text_node(&self, text: &str)->TextNode {
         TextNode {  text: text.to_string() }
     }
+    fn active_element(&self)->Option<ElementNode> {
+        None
+    }
     fn new()->Self {
         Document {}
     }
-    fn create_element(&self, tag: &'static str)->Self::ElementNode {
-        ElementNode {generic_node: Node {  }, tag}
+    fn create_element(&self, tag: impl Into<dom::Name>)->Self::ElementNode {
+        ElementNode {generic_node: Node {  }, tag: tag.into()}
     }
     fn log_1(s: &str) {
         println!("{}", s);
@@ -133,9 +190,12 @@ impl dom::TextNode for TextNode {
     fn new(text: &str)->Self {
         Self { text: text.to_string() }
     }
+    fn text_data(&self)->String {
+        self.text.clone()
+    }
 }
 
 pub fn render<F>(f:F) where F:FnMut(super::Visitor<ElementNode>) + 'static {
-    let body=crate::noop::ElementNode {generic_node: Node {}, tag: "body"};
+    let body=crate::noop::ElementNode {generic_node: Node {}, tag: "body".into()};
     super::EDOM::render(body, f);
 }
\ No newline at end of file
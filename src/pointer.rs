@@ -0,0 +1,232 @@
+use super::dom;
+use super::dom::Event;
+use super::keyboard::Modifiers;
+use super::visitor::Visitor;
+
+/// The device that caused a pointer event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PointerType {
+    /// A mouse, also used for plain mouse events.
+    #[default]
+    Mouse,
+    /// A pen or stylus.
+    Pen,
+    /// A finger on a touch screen.
+    Touch,
+}
+
+impl From<&str> for PointerType {
+    /// Parses the `pointerType` property of a DOM pointer event. Unknown devices are treated as mice.
+    fn from(s: &str)->Self {
+        match s {
+            "pen"=>PointerType::Pen,
+            "touch"=>PointerType::Touch,
+            _=>PointerType::Mouse,
+        }
+    }
+}
+
+/// The button that changed state in a mouse or pointer event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    /// Usually the left button.
+    #[default]
+    Primary,
+    /// Usually the wheel button.
+    Middle,
+    /// Usually the right button.
+    Secondary,
+    /// The browser back button.
+    Back,
+    /// The browser forward button.
+    Forward,
+}
+
+impl MouseButton {
+    /// The button of the DOM `MouseEvent.button` number.
+    pub fn from_index(button: i16)->Option<Self> {
+        match button {
+            0=>Some(MouseButton::Primary),
+            1=>Some(MouseButton::Middle),
+            2=>Some(MouseButton::Secondary),
+            3=>Some(MouseButton::Back),
+            4=>Some(MouseButton::Forward),
+            _=>None,
+        }
+    }
+
+    /// The bit of the button in [`PointerEvent::buttons`].
+    pub fn mask(self)->u16 {
+        match self {
+            MouseButton::Primary=>1,
+            MouseButton::Secondary=>2,
+            MouseButton::Middle=>4,
+            MouseButton::Back=>8,
+            MouseButton::Forward=>16,
+        }
+    }
+}
+
+/// The data of a mouse, pointer or wheel event that doesn't depend on the DOM backend.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointerEvent {
+    /// Position in the viewport.
+    pub client: (f64, f64),
+    /// Position relative to the padding edge of the target element.
+    pub offset: (f64, f64),
+    /// The button that was pressed or released, `None` for moves.
+    pub button: Option<MouseButton>,
+    /// The buttons held down, as a combination of [`MouseButton::mask`] bits.
+    pub buttons: u16,
+    /// The modifier keys that were held down.
+    pub modifiers: Modifiers,
+    /// The device that caused the event.
+    pub pointer_type: PointerType,
+    /// The horizontal and vertical scroll amount of a wheel event in pixels, zero for other events.
+    pub wheel_delta: (f64, f64),
+}
+
+impl PointerEvent {
+    /// An event of the mouse at `(x, y)` both in the viewport and in the target element, without
+    /// buttons.
+    pub fn at(x: f64, y: f64)->Self {
+        PointerEvent { client: (x, y), offset: (x, y), ..Self::default() }
+    }
+
+    /// Sets the button that is pressed, which is also added to [`PointerEvent::buttons`].
+    pub fn with_button(mut self, button: MouseButton)->Self {
+        self.button=Some(button);
+        self.buttons|=button.mask();
+        self
+    }
+
+    /// Sets the modifier keys.
+    pub fn with_modifiers(mut self, modifiers: Modifiers)->Self {
+        self.modifiers=modifiers;
+        self
+    }
+
+    /// Whether `button` is held down.
+    pub fn is_pressed(&self, button: MouseButton)->bool {
+        self.buttons & button.mask() != 0
+    }
+}
+
+impl<'d, 'e, 'c, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    fn track_pointer(&'c mut self, moves: bool) {
+        let mut position=self.pointer_position();
+        self.on("pointerenter", |e| position=e.pointer().map(|p| p.offset));
+        if moves {
+            self.on("pointermove", |e| position=e.pointer().map(|p| p.offset));
+        }
+        self.on("pointerleave", |_| position=None);
+        if position.is_some() || self.element.extras.is_some() {
+            self.element.extras_mut().pointer_position=position;
+        }
+    }
+
+    fn pointer_position(&self)->Option<(f64, f64)> {
+        self.element.extras.as_ref().and_then(|x| x.pointer_position)
+    }
+
+    /// Whether the pointer is over the current element:
+    /// ```
+    /// let app=edom::testing::TestApp::new(|mut root| {
+    ///     let mut button=root.button("Delete");
+    ///     let hovered=button.hovered();
+    ///     button.class(if hovered { "hovered" } else { "" });
+    /// });
+    /// let button=app.find_by_tag("button").unwrap();
+    /// app.pointer(&button, "pointerenter", edom::pointer::PointerEvent::at(1.0, 1.0));
+    /// assert_eq!(Some("hovered"), app.find_by_tag("button").unwrap().attribute("class"));
+    /// ```
+    pub fn hovered(&'c mut self)->bool {
+        self.track_pointer(false);
+        self.pointer_position().is_some()
+    }
+
+    /// The position of the pointer relative to the current element while it's over the element.
+    /// The application is rendered again whenever the pointer moves.
+    pub fn mouse_pos(&'c mut self)->Option<(f64, f64)> {
+        self.track_pointer(true);
+        self.pointer_position()
+    }
+
+    /// Returns the event in the render pass that is caused by pressing a button over the current element.
+    pub fn pointer_down(&'c mut self)->Option<PointerEvent> {
+        let mut r=None;
+        self.on("pointerdown", |e| r=e.pointer());
+        r
+    }
+
+    /// Returns true if the context menu was requested on the current element, usually with a right
+    /// click. The browser's menu is not shown.
+    pub fn right_clicked(&'c mut self)->bool {
+        let mut r=false;
+        self.on("contextmenu", |e| {
+            e.prevent_default();
+            r=true;
+        });
+        r
+    }
+
+    /// Returns the horizontal and vertical scroll amount in pixels in the render pass that is caused
+    /// by a wheel event on the current element.
+    pub fn wheel_delta(&'c mut self)->Option<(f64, f64)> {
+        let mut r=None;
+        self.on("wheel", |e| r=e.pointer().map(|p| p.wheel_delta));
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestApp;
+
+    #[test]
+    fn buttons() {
+        let e=PointerEvent::at(3.0, 4.0).with_button(MouseButton::Secondary).with_button(MouseButton::Primary);
+        assert!(e.is_pressed(MouseButton::Secondary));
+        assert!(!e.is_pressed(MouseButton::Middle));
+        assert_eq!(3, e.buttons);
+        assert_eq!(Some(MouseButton::Back), MouseButton::from_index(3));
+        assert_eq!(PointerType::Touch, PointerType::from("touch"));
+    }
+
+    #[test]
+    fn canvas() {
+        let mut log=Vec::new();
+        let mut zoom=1.0;
+        let app=TestApp::new(move |mut root| {
+            root.div(|canvas| {
+                canvas.id("canvas");
+                let pos=canvas.mouse_pos();
+                if let Some(e)=canvas.pointer_down() {
+                    log.push(format!("down {:?} {:?}", e.button, e.pointer_type));
+                }
+                if canvas.right_clicked() {
+                    log.push("menu".to_string());
+                }
+                if let Some((_, dy))=canvas.wheel_delta() {
+                    zoom*=if dy < 0.0 { 2.0 } else { 0.5 };
+                }
+                canvas.text(format!("{:?} {} {}", pos, zoom, log.join(",")).as_str());
+            });
+        });
+        let canvas=app.find_by_id("canvas").unwrap();
+        let text=|| app.find_by_id("canvas").unwrap().text;
+        assert_eq!("None 1 ", text());
+        app.pointer(&canvas, "pointerenter", PointerEvent::at(0.0, 5.0));
+        assert_eq!("Some((0.0, 5.0)) 1 ", text());
+        app.pointer(&canvas, "pointermove", PointerEvent { offset: (10.0, 5.0), ..PointerEvent::at(20.0, 5.0) });
+        assert_eq!("Some((10.0, 5.0)) 1 ", text());
+        app.pointer(&canvas, "pointerdown", PointerEvent { pointer_type: PointerType::Pen,
+            ..PointerEvent::at(10.0, 5.0).with_button(MouseButton::Primary) });
+        assert!(!app.right_click(&canvas));
+        app.pointer(&canvas, "wheel", PointerEvent { wheel_delta: (0.0, -100.0), ..PointerEvent::at(10.0, 5.0) });
+        assert_eq!("Some((10.0, 5.0)) 2 down Some(Primary) Pen,menu", text());
+        app.pointer(&canvas, "pointerleave", PointerEvent::at(-1.0, 5.0));
+        assert_eq!("None 2 down Some(Primary) Pen,menu", text());
+    }
+}
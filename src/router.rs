@@ -0,0 +1,484 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsCast;
+
+use super::dom;
+use super::visitor::Visitor;
+
+/// A parsed URL of the application: the path and the query string, without the origin and the hash
+/// sign of [`HashHistory`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Url {
+    segments: Vec<String>,
+    query: Vec<(String, String)>,
+}
+
+impl Url {
+    /// Parses a URL like `/users/12?tab=posts`. Segments and query values are percent-decoded.
+    pub fn parse(url: &str)->Self {
+        let (path, query)=url.split_once('?').unwrap_or((url, ""));
+        let segments=path.split('/').filter(|s| !s.is_empty()).map(decode).collect();
+        let query=query.split('&').filter(|s| !s.is_empty()).map(|pair| {
+            let (k, v)=pair.split_once('=').unwrap_or((pair, ""));
+            (decode(&k.replace('+', " ")), decode(&v.replace('+', " ")))
+        }).collect();
+        Url { segments, query }
+    }
+
+    /// A URL with the given path segments, which are encoded when the URL is written.
+    pub fn from_segments<S: AsRef<str>>(segments: &[S])->Self {
+        Url { segments: segments.iter().map(|s| s.as_ref().to_string()).collect(), query: Vec::new() }
+    }
+
+    /// Adds a query parameter.
+    pub fn with_query(mut self, name: &str, value: &str)->Self {
+        self.query.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The decoded segments of the path (`["users", "12"]` for `/users/12`).
+    pub fn segments(&self)->Vec<&str> {
+        self.segments.iter().map(|s| s.as_str()).collect()
+    }
+
+    /// The first value of the query parameter `name`.
+    pub fn query(&self, name: &str)->Option<&str> {
+        self.query.iter().find(|q| q.0 == name).map(|q| q.1.as_str())
+    }
+
+    /// Matches the path against `pattern` like `/users/:id/posts`. Segments starting with `:` match
+    /// any segment and are returned by their names.
+    pub fn params(&self, pattern: &str)->Option<HashMap<String, String>> {
+        let pattern: Vec<&str>=pattern.split('/').filter(|s| !s.is_empty()).collect();
+        if pattern.len() != self.segments.len() {
+            return None;
+        }
+        let mut params=HashMap::new();
+        for (p, s) in pattern.iter().zip(&self.segments) {
+            if let Some(name)=p.strip_prefix(':') {
+                params.insert(name.to_string(), s.clone());
+            } else if p != s {
+                return None;
+            }
+        }
+        Some(params)
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+        if self.segments.is_empty() {
+            write!(f, "/")?;
+        }
+        for s in &self.segments {
+            write!(f, "/{}", encode(s))?;
+        }
+        for (i, (k, v)) in self.query.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { '?' } else { '&' }, encode(k), encode(v))?;
+        }
+        Ok(())
+    }
+}
+
+/// Percent-encodes everything except unreserved characters.
+pub fn encode(s: &str)->String {
+    let mut r=String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            r.push(b as char);
+        } else {
+            r.push_str(&format!("%{:02X}", b));
+        }
+    }
+    r
+}
+
+fn decode(s: &str)->String {
+    let bytes=s.as_bytes();
+    let mut r=Vec::new();
+    let mut i=0;
+    while i < bytes.len() {
+        let hex=bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b))=>{
+                r.push(b);
+                i+=3;
+            },
+            (b, _)=>{
+                r.push(b);
+                i+=1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&r).into_owned()
+}
+
+/// A route type of the application, converted from and to [`Url`]s:
+/// ```
+/// use edom::router::{Route, Url};
+/// #[derive(Debug, PartialEq)]
+/// enum Page { Home, User(u64), NotFound }
+/// impl Route for Page {
+///     fn from_url(url: &Url)->Self {
+///         match url.segments().as_slice() {
+///             []=>Page::Home,
+///             ["users", id]=>id.parse().map(Page::User).unwrap_or(Page::NotFound),
+///             _=>Page::NotFound,
+///         }
+///     }
+///     fn to_url(&self)->Url {
+///         match self {
+///             Page::User(id)=>Url::from_segments(&["users", &id.to_string()]),
+///             _=>Url::default(),
+///         }
+///     }
+/// }
+/// assert_eq!(Page::User(12), Page::from_url(&Url::parse("/users/12?tab=posts")));
+/// ```
+pub trait Route {
+    /// The route of a URL; unknown URLs should be mapped to a not found route.
+    fn from_url(url: &Url)->Self;
+    /// The URL of the route.
+    fn to_url(&self)->Url;
+}
+
+/// Where the current URL of the application is stored.
+pub trait History {
+    /// The current path and query string.
+    fn url(&self)->String;
+    /// The `href` of a link to `url`.
+    fn href(&self, url: &str)->String;
+    /// Navigates to `url` without loading a page, adding a history entry.
+    fn push(&self, url: &str);
+    /// Navigates to `url` without loading a page, replacing the current history entry.
+    fn replace(&self, url: &str);
+    /// Calls `callback` when the URL is changed by the user (back and forward buttons, editing the URL).
+    fn listen(&self, callback: Box<dyn FnMut()>);
+}
+
+fn listen_window(event: &str, mut callback: Box<dyn FnMut()>) {
+    let closure=Closure::wrap(Box::new(move |_: web_sys::Event| callback()) as Box<dyn FnMut(_)>);
+    web_sys::window().unwrap().add_event_listener_with_callback(event, closure.as_ref().unchecked_ref()).unwrap();
+    closure.forget();
+}
+
+fn push_state(url: &str, replace: bool) {
+    let history=web_sys::window().unwrap().history().unwrap();
+    if replace {
+        history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(url)).unwrap();
+    } else {
+        history.push_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(url)).unwrap();
+    }
+}
+
+/// Stores the URL after the hash sign (`index.html#/users/12`), so it works with static file servers.
+pub struct HashHistory {
+}
+
+impl History for HashHistory {
+    fn url(&self)->String {
+        let hash=web_sys::window().unwrap().location().hash().unwrap_or_default();
+        match hash.strip_prefix('#') {
+            Some(url) if !url.is_empty()=>url.to_string(),
+            _=>"/".to_string(),
+        }
+    }
+    fn href(&self, url: &str)->String {
+        format!("#{}", url)
+    }
+    fn push(&self, url: &str) {
+        push_state(self.href(url).as_str(), false);
+    }
+    fn replace(&self, url: &str) {
+        push_state(self.href(url).as_str(), true);
+    }
+    fn listen(&self, callback: Box<dyn FnMut()>) {
+        listen_window("hashchange", callback);
+    }
+}
+
+/// Stores the URL in the path using the History API; the server has to serve the application
+/// for all its URLs.
+pub struct BrowserHistory {
+}
+
+impl History for BrowserHistory {
+    fn url(&self)->String {
+        let location=web_sys::window().unwrap().location();
+        location.pathname().unwrap_or_default() + location.search().unwrap_or_default().as_str()
+    }
+    fn href(&self, url: &str)->String {
+        url.to_string()
+    }
+    fn push(&self, url: &str) {
+        push_state(url, false);
+    }
+    fn replace(&self, url: &str) {
+        push_state(url, true);
+    }
+    fn listen(&self, callback: Box<dyn FnMut()>) {
+        listen_window("popstate", callback);
+    }
+}
+
+#[derive(Default)]
+struct MemoryHistoryState {
+    entries: Vec<String>,
+    index: usize,
+    listeners: Vec<Box<dyn FnMut()>>,
+}
+
+/// Keeps the history entries in memory, for running routers natively in tests.
+/// Clones share the same history.
+#[derive(Clone)]
+pub struct MemoryHistory {
+    state: Rc<RefCell<MemoryHistoryState>>,
+}
+
+impl MemoryHistory {
+    /// A history with only `url` in it.
+    pub fn new(url: &str)->Self {
+        let state=MemoryHistoryState { entries: vec![url.to_string()], ..Default::default() };
+        MemoryHistory { state: Rc::new(RefCell::new(state)) }
+    }
+
+    /// Goes back one entry like the back button of the browser. Returns false if there's nothing before.
+    pub fn back(&self)->bool {
+        self.go(-1)
+    }
+
+    /// Goes forward one entry like the forward button of the browser.
+    pub fn forward(&self)->bool {
+        self.go(1)
+    }
+
+    fn go(&self, delta: isize)->bool {
+        {
+            let mut state=self.state.borrow_mut();
+            match state.index.checked_add_signed(delta) {
+                Some(index) if index < state.entries.len()=>state.index=index,
+                _=>return false,
+            }
+        }
+        self.notify();
+        true
+    }
+
+    /// Navigates to `url` like the user editing the address bar.
+    pub fn set_url(&self, url: &str) {
+        self.push(url);
+        self.notify();
+    }
+
+    fn notify(&self) {
+        // The listeners are taken out while they run, so they can read the history.
+        let mut listeners=std::mem::take(&mut self.state.borrow_mut().listeners);
+        for listener in &mut listeners {
+            listener();
+        }
+        let mut state=self.state.borrow_mut();
+        listeners.append(&mut state.listeners);
+        state.listeners=listeners;
+    }
+}
+
+impl History for MemoryHistory {
+    fn url(&self)->String {
+        let state=self.state.borrow();
+        state.entries[state.index].clone()
+    }
+    fn href(&self, url: &str)->String {
+        url.to_string()
+    }
+    fn push(&self, url: &str) {
+        let mut state=self.state.borrow_mut();
+        let index=state.index + 1;
+        state.entries.truncate(index);
+        state.entries.push(url.to_string());
+        state.index=index;
+    }
+    fn replace(&self, url: &str) {
+        let mut state=self.state.borrow_mut();
+        let index=state.index;
+        state.entries[index]=url.to_string();
+    }
+    fn listen(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().listeners.push(callback);
+    }
+}
+
+/// Reads the current route of type `R` from a [`History`] and navigates to other routes.
+/// It's passed to [`Visitor::route`] in every render, which re-renders the application when the URL
+/// is changed by the user.
+pub struct Router<R> {
+    history: Rc<dyn History>,
+    listening: bool,
+    _route: PhantomData<fn()->R>,
+}
+
+impl<R> Router<R> where R: Route {
+    /// A router using `history` as the source of URLs.
+    pub fn new<H>(history: H)->Self where H: History + 'static {
+        Router { history: Rc::new(history), listening: false, _route: PhantomData }
+    }
+
+    /// A router that keeps the URL after the hash sign.
+    pub fn hash()->Self {
+        Self::new(HashHistory {})
+    }
+
+    /// A router that keeps the URL in the path.
+    pub fn browser()->Self {
+        Self::new(BrowserHistory {})
+    }
+
+    /// The history used by the router.
+    pub fn history(&self)->&Rc<dyn History> {
+        &self.history
+    }
+
+    /// The route of the current URL.
+    pub fn route(&self)->R {
+        R::from_url(&Url::parse(self.history.url().as_str()))
+    }
+
+    /// Navigates to `route`. When called outside of an event handler, the application is not
+    /// re-rendered automatically.
+    pub fn navigate(&self, route: &R) {
+        self.history.push(route.to_url().to_string().as_str());
+    }
+
+    /// Navigates to `route` without adding a history entry.
+    pub fn redirect(&self, route: &R) {
+        self.history.replace(route.to_url().to_string().as_str());
+    }
+}
+
+impl<'d, 'e, 'f, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Returns the current route of `router`. The first call starts listening to URL changes and
+    /// makes `router` the one used by [`Visitor::link`].
+    pub fn route<R>(&mut self, router: &mut Router<R>)->R where R: Route {
+        if !router.listening {
+            let rerender=self.edom.rerender.clone();
+            router.history.listen(Box::new(move || rerender.borrow_mut()()));
+            router.listening=true;
+            self.edom.history=Some(router.history.clone());
+        }
+        router.route()
+    }
+
+    /// A link to `route` that navigates without loading a page when it's clicked.
+    /// Without a router (see [`Visitor::route`]) it's a normal link to the URL of the route.
+    /// The link may be rendered before the router in the first render, like in a navigation bar.
+    pub fn link<R>(&'f mut self, route: &R, text: &str)->Visitor<'f, 'f, EN> where R: Route {
+        let url=route.to_url().to_string();
+        let history=self.edom.history.clone();
+        let href=match &history {
+            Some(history)=>history.href(url.as_str()),
+            None=>url.clone(),
+        };
+        let mut r=self.element("a");
+        r.attr("href", href.as_str());
+        // Listeners are only added when the element is created, so the click handler is added even
+        // if there's no router yet.
+        r.on("click", |e| {
+            if let Some(history)=&history {
+                dom::Event::prevent_default(e);
+                history.push(url.as_str());
+            }
+        });
+        r.text(text);
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestApp;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Filter {
+        All,
+        Active,
+        Search(String),
+    }
+
+    impl Route for Filter {
+        fn from_url(url: &Url)->Self {
+            match url.segments().as_slice() {
+                ["active"]=>Filter::Active,
+                ["search"]=>Filter::Search(url.query("q").unwrap_or_default().to_string()),
+                _=>Filter::All,
+            }
+        }
+        fn to_url(&self)->Url {
+            match self {
+                Filter::All=>Url::default(),
+                Filter::Active=>Url::from_segments(&["active"]),
+                Filter::Search(q)=>Url::from_segments(&["search"]).with_query("q", q),
+            }
+        }
+    }
+
+    #[test]
+    fn urls() {
+        let url=Url::parse("/users/a%20b/posts?tab=new+ones&x");
+        assert_eq!(vec!["users", "a b", "posts"], url.segments());
+        assert_eq!(Some("new ones"), url.query("tab"));
+        assert_eq!(Some(""), url.query("x"));
+        let params=url.params("/users/:name/posts").unwrap();
+        assert_eq!("a b", params["name"]);
+        assert_eq!(None, url.params("/users/:name"));
+        assert_eq!("/users/a%20b/posts?tab=new%20ones&x=", url.to_string());
+        assert_eq!("/", Url::parse("").to_string());
+        assert_eq!(Filter::Search("é&".to_string()),
+            Filter::from_url(&Url::parse(Filter::Search("é&".to_string()).to_url().to_string().as_str())));
+    }
+
+    #[test]
+    fn links_and_history() {
+        let history=MemoryHistory::new("/active");
+        let mut router=Router::<Filter>::new(history.clone());
+        let app=TestApp::new(move |mut root| {
+            let filter=root.route(&mut router);
+            root.link(&Filter::All, "All");
+            root.link(&Filter::Search("a b".to_string()), "Search");
+            root.text(format!("{:?}", filter).as_str());
+        });
+        let text=|| app.body().children()[2].text_content();
+        assert_eq!("Active", text());
+        let search=app.find_by_text("Search").unwrap();
+        assert_eq!(Some("/search?q=a%20b"), search.attribute("href"));
+        assert!(!app.click(&search));
+        assert_eq!("Search(\"a b\")", text());
+        assert_eq!("/search?q=a%20b", history.url());
+        assert!(history.back());
+        assert_eq!("Active", text());
+        assert!(!history.back());
+        history.set_url("/");
+        assert_eq!("All", text());
+        assert!(!history.forward());
+    }
+
+    #[test]
+    fn link_before_route() {
+        let history=MemoryHistory::new("/");
+        let mut router=Router::<Filter>::new(history.clone());
+        let app=TestApp::new(move |mut root| {
+            root.div(|nav| { nav.link(&Filter::Active, "Active"); });
+            let filter=root.route(&mut router);
+            root.text(format!("{:?}", filter).as_str());
+        });
+        let active=app.find_by_text("Active").unwrap();
+        assert!(!app.click(&active));
+        assert_eq!("Active", app.body().children()[1].text_content());
+        assert_eq!("/active", history.url());
+    }
+}
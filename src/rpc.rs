@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::dom;
+use super::fetch::{self, Client, Fetch, Request, Response, ResponseFuture, Transport};
+use super::visitor::Visitor;
+
+/// A remote function, declared once and used by both the client and the [`Server`].
+/// Calls are sent as `POST` requests to [`Rpc::PATH`] with JSON bodies:
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Add {a: i64, b: i64}
+///
+/// struct AddNumbers;
+/// impl edom::rpc::Rpc for AddNumbers {
+///     const PATH: &'static str = "/rpc/add";
+///     type Request = Add;
+///     type Response = i64;
+/// }
+///
+/// let mut server=edom::rpc::Server::new();
+/// server.register::<AddNumbers, _>(|add| Ok(add.a + add.b));
+/// let client=edom::fetch::Client::new(edom::rpc::LocalTransport::new(server));
+///
+/// let mut sum=edom::fetch::Fetch::new();
+/// let app=edom::testing::TestApp::new(move |mut root| {
+///     if root.button("Add").clicked() {
+///         root.call::<AddNumbers>(&client, &mut sum, &Add {a: 1, b: 2});
+///     }
+///     let text=sum.ready().map(|sum| sum.to_string());
+///     root.text(text.as_deref().unwrap_or("?"));
+/// });
+/// app.click(&app.find_by_text("Add").unwrap());
+/// app.run_until_stalled();
+/// assert!(app.html().ends_with("3"));
+/// ```
+pub trait Rpc {
+    /// The path that the server dispatches on.
+    const PATH: &'static str;
+    /// The parameters of the call.
+    type Request: Serialize + DeserializeOwned;
+    /// The result of the call.
+    type Response: Serialize + DeserializeOwned;
+}
+
+impl Client {
+    /// Calls the remote function `R` with `request`.
+    /// Errors returned by the handler on the server are [`fetch::Error::Status`] errors.
+    pub fn call<R>(&self, request: &R::Request)->impl Future<Output=Result<R::Response, fetch::Error>>
+            where R: Rpc {
+        self.post(R::PATH).json(request).send_json()
+    }
+}
+
+impl<'d, 'e, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Calls the remote function `R` and stores the result in `result`, re-rendering when it arrives
+    /// (see [`Visitor::spawn`]).
+    pub fn call<R>(&self, client: &Client, result: &mut Fetch<R::Response>, request: &R::Request)
+            where R: Rpc + 'static, R::Response: 'static {
+        self.spawn(result, client.call::<R>(request));
+    }
+}
+
+type Handler = Box<dyn Fn(&str)->Response>;
+
+/// Dispatches requests to the handlers of remote functions by their paths.
+/// It doesn't depend on an HTTP server: requests have to be converted to [`fetch::Request`]s
+/// and the returned [`fetch::Response`]s have to be sent back.
+#[derive(Default)]
+pub struct Server {
+    handlers: HashMap<&'static str, Handler>,
+}
+
+impl Server {
+    /// A server without handlers.
+    pub fn new()->Self {
+        Self::default()
+    }
+
+    /// Handles the calls of `R` with `handler`. An `Err` is sent with status 500 and the message as body.
+    pub fn register<R, F>(&mut self, handler: F)->&mut Self
+            where R: Rpc, F: Fn(R::Request)->Result<R::Response, String> + 'static {
+        self.handlers.insert(R::PATH, Box::new(move |body| {
+            let request=match serde_json::from_str(body) {
+                Ok(request)=>request,
+                Err(e)=>return Response::new(400, e.to_string()),
+            };
+            match handler(request) {
+                Ok(response)=>match serde_json::to_string(&response) {
+                    Ok(body)=>Response::new(200, body),
+                    Err(e)=>Response::new(500, e.to_string()),
+                },
+                Err(e)=>Response::new(500, e),
+            }
+        }));
+        self
+    }
+
+    /// Answers a request: 404 for unknown paths, 405 for other methods than `POST`,
+    /// 400 if the body can't be decoded.
+    pub fn handle(&self, request: &Request)->Response {
+        let path=request.url.split('?').next().unwrap_or_default();
+        let Some(handler)=self.handlers.get(path) else {
+            return Response::new(404, format!("No remote function at {}", path));
+        };
+        if request.method != "POST" {
+            return Response::new(405, "Remote functions are called with POST");
+        }
+        handler(request.body.as_deref().unwrap_or_default())
+    }
+}
+
+/// Sends the requests to a [`Server`] in the same process, so client and server code can be
+/// tested together without a network. The responses are ready when the executor first polls them.
+#[derive(Clone)]
+pub struct LocalTransport {
+    server: Rc<Server>,
+}
+
+impl LocalTransport {
+    /// A transport that sends the requests to `server`.
+    pub fn new(server: Server)->Self {
+        LocalTransport { server: Rc::new(server) }
+    }
+}
+
+impl Transport for LocalTransport {
+    fn send(&self, request: Request)->ResponseFuture {
+        Box::pin(std::future::ready(Ok(self.server.handle(&request))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::TaskState;
+    use crate::testing::TestApp;
+
+    #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+    struct Todo {
+        id: u64,
+        title: String,
+    }
+
+    struct AddTodo;
+    impl Rpc for AddTodo {
+        const PATH: &'static str = "/rpc/add_todo";
+        type Request = String;
+        type Response = Todo;
+    }
+
+    struct ListTodos;
+    impl Rpc for ListTodos {
+        const PATH: &'static str = "/rpc/list_todos";
+        type Request = ();
+        type Response = Vec<Todo>;
+    }
+
+    fn server()->Server {
+        let todos=Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut server=Server::new();
+        let moved_todos=todos.clone();
+        server.register::<AddTodo, _>(move |title| {
+            if title.is_empty() {
+                return Err("Empty title".to_string());
+            }
+            let mut todos=moved_todos.borrow_mut();
+            let todo=Todo { id: todos.len() as u64, title };
+            todos.push(todo.clone());
+            Ok(todo)
+        }).register::<ListTodos, _>(move |()| Ok(todos.borrow().clone()));
+        server
+    }
+
+    #[test]
+    fn dispatch() {
+        let server=server();
+        let request=|method: &str, url: &str, body: &str| Request { method: method.to_string(),
+            url: url.to_string(), headers: Vec::new(), body: Some(body.to_string()) };
+        assert_eq!(Response::new(200, r#"{"id":0,"title":"a"}"#), server.handle(&request("POST", "/rpc/add_todo", "\"a\"")));
+        assert_eq!(Response::new(500, "Empty title"), server.handle(&request("POST", "/rpc/add_todo", "\"\"")));
+        assert_eq!(400, server.handle(&request("POST", "/rpc/add_todo", "1")).status);
+        assert_eq!(405, server.handle(&request("GET", "/rpc/add_todo", "\"a\"")).status);
+        assert_eq!(404, server.handle(&request("POST", "/rpc/remove_todo", "0")).status);
+        assert_eq!(Response::new(200, r#"[{"id":0,"title":"a"}]"#), server.handle(&request("POST", "/rpc/list_todos?x=1", "null")));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let client=Client::new(LocalTransport::new(server()));
+        let mut title=String::new();
+        let mut added=Fetch::new();
+        let mut todos=Fetch::new();
+        let app=TestApp::new(move |mut root| {
+            root.text_input(&mut title).id("title");
+            if root.button("Add").clicked() {
+                root.call::<AddTodo>(&client, &mut added, &title);
+            }
+            if let TaskState::Ready(_)=added.take() {
+                root.call::<ListTodos>(&client, &mut todos, &());
+            }
+            let text=match &*todos.state() {
+                TaskState::Ready(todos)=>todos.iter().map(|t| t.title.as_str()).collect::<Vec<_>>().join(","),
+                TaskState::Err(e)=>e.to_string(),
+                _=>String::new(),
+            };
+            root.div(|div| div.text(text.as_str()));
+        });
+        let (input, add)=(app.find_by_id("title").unwrap(), app.find_by_text("Add").unwrap());
+        for title in ["a", "b"] {
+            app.input(&input, title);
+            app.click(&add);
+            app.run_until_stalled();
+        }
+        assert_eq!("a,b", app.find_by_tag("div").unwrap().text);
+        app.input(&input, "");
+        app.click(&add);
+        app.run_until_stalled();
+        assert_eq!("a,b", app.find_by_tag("div").unwrap().text);
+    }
+}
@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::hash::Hash;
+
+use super::dom;
+use super::dom::Event;
+use super::visitor::Visitor;
+
+impl<'d, 'e, 'f, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Like [`Visitor::for_each`], but the rows can be dragged to another position with HTML5 drag
+    /// and drop. In the render pass of a drop it returns the key of the dragged row and the index of
+    /// the row it was dropped on (in the rendered list), where the item should be moved to.
+    /// The application reorders its items, and the keyed diff of the next pass moves the DOM node:
+    /// ```
+    /// let mut tasks=vec![(1, "Write"), (2, "Test"), (3, "Ship")];
+    /// let app=edom::testing::TestApp::new(move |mut root| {
+    ///     let moved=root.sortable_for_each(tasks.iter(), |t| t.0, "li", |t, li| {
+    ///         li.text(t.1);
+    ///     });
+    ///     if let Some((id, to))=moved {
+    ///         let from=tasks.iter().position(|t| t.0 == id).unwrap();
+    ///         let task=tasks.remove(from);
+    ///         tasks.insert(to, task);
+    ///     }
+    /// });
+    /// let rows=app.find_all_by_tag("li");
+    /// app.drag(&rows[2], &rows[0]);
+    /// assert_eq!("ShipWriteTest", app.body().text_content());
+    /// ```
+    /// A drop from another list is ignored.
+    pub fn sortable_for_each<FIdx, TIdx, FCB, I, L>(&'f mut self, list: L, fidx: FIdx, tag: impl Into<dom::Name>,
+            mut fcb: FCB)->Option<(TIdx, usize)>
+            where FIdx: FnMut(&I)->TIdx, TIdx: Eq + Hash + Clone, FCB: FnMut(I, &mut Visitor<EN>),
+            L: Iterator<Item=I> {
+        let fidx=RefCell::new(fidx);
+        let mut keys: Vec<(u64, TIdx)>=Vec::new();
+        let mut dropped: Option<(u64, usize)>=None;
+        self.for_each(list, |item| fidx.borrow_mut()(item), tag, |item, row| {
+            let key=fidx.borrow_mut()(&item);
+            let uid=row.element.uid;
+            row.attr("draggable", "true");
+            let mut started=false;
+            row.on("dragstart", |_| started=true);
+            // The drop target has to cancel dragover, otherwise dropping is not allowed.
+            row.on("dragover", |e| e.prevent_default());
+            let mut drop=false;
+            row.on("drop", |e| {
+                e.prevent_default();
+                drop=true;
+            });
+            let mut ended=false;
+            row.on("dragend", |_| ended=true);
+            if started {
+                row.edom.dragged=Some(uid);
+            }
+            if drop {
+                if let Some(dragged)=row.edom.dragged.take() {
+                    dropped=Some((dragged, keys.len()));
+                }
+            }
+            if ended && row.edom.dragged == Some(uid) {
+                row.edom.dragged=None;
+            }
+            keys.push((uid, key));
+            fcb(item, row);
+        });
+        let (dragged, to)=dropped?;
+        keys.into_iter().find(|k| k.0 == dragged).map(|(_, key)| (key, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TestApp;
+
+    #[test]
+    fn kanban() {
+        let mut columns=vec![vec![1, 2, 3, 4], vec![5]];
+        let app=TestApp::new(move |mut root| {
+            let mut moves=Vec::new();
+            for column in &columns {
+                root.ul(|ul| {
+                    moves.push(ul.sortable_for_each(column.iter(), |i| **i, "li", |i, li| {
+                        li.text(i.to_string().as_str());
+                    }));
+                });
+            }
+            for (column, moved) in columns.iter_mut().zip(moves) {
+                if let Some((item, to))=moved {
+                    let from=column.iter().position(|i| *i == item).unwrap();
+                    let item=column.remove(from);
+                    column.insert(to, item);
+                }
+            }
+        });
+        let rows=app.find_all_by_tag("li");
+        let dnodes=|| rows.iter().map(|r| app.dnode(r).unwrap()).collect::<Vec<_>>();
+        let before=dnodes();
+        let text=|| app.body().child_elements().iter().map(|ul| ul.text_content()).collect::<Vec<_>>();
+        app.drag(&rows[0], &rows[2]);
+        assert_eq!(vec!["2314", "5"], text());
+        // The rows are moved, not recreated.
+        assert!(before == dnodes());
+        app.drag(&rows[3], &rows[1]);
+        assert_eq!(vec!["4231", "5"], text());
+        // Dropping into another list doesn't move anything.
+        app.drag(&rows[1], &rows[4]);
+        assert_eq!(vec!["4231", "5"], text());
+        assert_eq!(Some("true"), app.find_by_tag("li").unwrap().attribute("draggable"));
+    }
+}
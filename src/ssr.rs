@@ -0,0 +1,76 @@
+use super::noop;
+use super::vdom;
+use super::{Visitor, EDOM};
+
+/// Runs the render function once without a browser and returns the HTML of the children of `body`.
+/// The function doesn't need to be `'static`, as no events are handled:
+/// ```
+/// let items=vec!["a", "b"];
+/// let html=edom::ssr::render_to_string(|mut root| {
+///     root.h1().text("Items");
+///     root.ul(|ul| ul.for_each(items.iter(), |i| **i, "li", |i, li| li.text(i)));
+///     root.br();
+/// });
+/// assert_eq!("<h1>Items</h1><ul><li>a</li><li>b</li></ul><br>", html);
+/// ```
+pub fn render_to_string<F>(f: F)->String where F: FnMut(Visitor<noop::ElementNode>) {
+    let mut s=String::new();
+    render_to(&mut s, f);
+    s
+}
+
+/// Like [`render_to_string`], but appends the HTML to `s`.
+pub fn render_to<F>(s: &mut String, f: F) where F: FnMut(Visitor<noop::ElementNode>) {
+    let body=noop::ElementNode { tag: "body".into(), generic_node: noop::Node {} };
+    let mut edom=EDOM::new(vdom::Element::new("body", Some(body), 0));
+    assert_eq!(0, edom.next_uid());
+    edom.render_once(f);
+    edom.get_root().render_children_to(s);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn attributes_and_escaping() {
+        let mut name="<Arthur & \"Ford\">".to_string();
+        let mut done=false;
+        let mut shown=true;
+        let html=render_to_string(|mut root| {
+            root.text_input(&mut name).autofocus(true);
+            root.checkbox(&mut done);
+            root.checkbox(&mut shown).id("shown");
+            root.texttextarea(&mut name);
+            root.text(name.as_str());
+            root.render_element_if(false, "span", |_| ());
+        });
+        assert_eq!("<input value=\"&lt;Arthur &amp; &quot;Ford&quot;&gt;\" type=\"text\" autofocus>\
+            <input type=\"checkbox\"><input checked type=\"checkbox\" id=\"shown\">\
+            <textarea>&lt;Arthur &amp; \"Ford\"&gt;</textarea>&lt;Arthur &amp; \"Ford\"&gt;", html);
+    }
+
+    #[test]
+    fn names_from_data() {
+        let tags=vec!["my-card".to_string(), "p".to_string()];
+        let html=render_to_string(|mut root| {
+            for tag in &tags {
+                root.element(tag.clone()).attr(format!("data-{}", tag), "1");
+            }
+            // An attribute added to the element directly is not checked by the visitor.
+            root.div(|div| div.element.attr.push(("x onload=alert(1)".into(), Rc::new(String::new()))));
+        });
+        assert_eq!("<my-card data-my-card=\"1\"></my-card><p data-p=\"1\"></p><div></div>", html);
+    }
+
+    #[test]
+    fn invalid_names() {
+        let html=render_to_string(|mut root| {
+            root.div(|div| { div.attr(String::from("x onload=alert(1)"), ""); });
+            root.element(String::new()).attr(String::from("a\"b"), "1").text("!");
+        });
+        assert_eq!("<div></div><div>!</div>", html);
+    }
+}
@@ -0,0 +1,97 @@
+use super::dom;
+use super::visitor::Visitor;
+
+impl<'d, 'e, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// A value that belongs to the current element and is kept between renders, so reusable widgets
+    /// don't need their callers to own their internal state:
+    /// ```
+    /// use edom::{ElementNode, Visitor};
+    /// fn collapsible<EN: ElementNode>(v: &mut Visitor<EN>, title: &str, mut fcb: impl FnMut(&mut Visitor<EN>)) {
+    ///     v.div(|panel| {
+    ///         let open=panel.state::<bool>();
+    ///         if panel.button(title).clicked() {
+    ///             *open=!*open;
+    ///         }
+    ///         panel.render_if(*open, &mut fcb);
+    ///     });
+    /// }
+    /// let app=edom::testing::TestApp::new(|mut root| {
+    ///     collapsible(&mut root, "Details", |panel| panel.text("Made in 2024"));
+    ///     collapsible(&mut root, "Reviews", |panel| panel.text("None yet"));
+    /// });
+    /// app.click(&app.find_by_text("Reviews").unwrap());
+    /// assert_eq!("DetailsReviewsNone yet", app.body().text_content());
+    /// ```
+    /// The values of an element are identified by the order of the calls, so `state` shouldn't be
+    /// called conditionally. If the type at a position changes, the value starts from the default again.
+    /// The values are dropped with the element when its [`Visitor::for_each`] row is removed, and when
+    /// it (or an ancestor) is hidden by [`Visitor::render_element_if`].
+    pub fn state<T: Default + 'static>(&mut self)->&'e mut T {
+        let pos=self.statepos;
+        self.statepos+=1;
+        let state=&mut self.element.extras_mut().state;
+        if pos == state.len() {
+            state.push(Box::new(T::default()));
+        } else if !state[pos].is::<T>() {
+            state[pos]=Box::new(T::default());
+        }
+        let value: *mut T=state[pos].downcast_mut::<T>().unwrap();
+        // The value is boxed, so it stays in place while the element lives, and each call of a
+        // render pass returns a different value.
+        unsafe { &mut *value }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestApp;
+
+    fn counter<EN: dom::ElementNode>(v: &mut Visitor<EN>) {
+        v.span(|span| {
+            let count=span.state::<u32>();
+            let name=span.state::<String>();
+            if span.button("+").clicked() {
+                *count+=1;
+                name.push('!');
+            }
+            span.text(format!("{}{}", name, count).as_str());
+        });
+    }
+
+    #[test]
+    fn kept_between_renders() {
+        let mut rows=vec![1, 2];
+        let mut shown=true;
+        let app=TestApp::new(move |mut root| {
+            if root.button("toggle").clicked() {
+                shown=!shown;
+            }
+            if root.button("remove").clicked() {
+                rows.retain(|r| *r != 2);
+            }
+            if root.button("add").clicked() {
+                rows.push(rows.len() as i32 + 2);
+            }
+            root.render_element_if(shown, "div", counter);
+            root.ul(|ul| ul.for_each(rows.iter(), |r| **r, "li", |_, li| counter(li)));
+        });
+        let counts=|| app.find_all_by_tag("span").iter().map(|s| s.text.clone()).collect::<Vec<_>>().join(",");
+        let click=|text: &str, i: usize| app.click(&app.find_all_by_text(text)[i]);
+        click("+", 0);
+        click("+", 0);
+        click("+", 2);
+        assert_eq!("!!2,0,!1", counts());
+        // Hiding drops the state.
+        click("toggle", 0);
+        assert_eq!("0,!1", counts());
+        click("toggle", 0);
+        assert_eq!("0,0,!1", counts());
+        // Removed rows lose their state, and a cloned row doesn't get the state of the original.
+        click("+", 1);
+        click("remove", 0);
+        assert_eq!("0,!1", counts());
+        click("add", 0);
+        assert_eq!("0,!1,0", counts());
+    }
+}
@@ -0,0 +1,208 @@
+use std::cell::{Ref, RefCell};
+use std::future::Future;
+use std::rc::Rc;
+
+use super::dom::{self, Executor};
+use super::visitor::Visitor;
+
+/// State of a [`Task`].
+#[derive(Debug, PartialEq)]
+pub enum TaskState<T, E> {
+    /// Nothing was spawned yet.
+    Idle,
+    /// The future is running.
+    Pending,
+    /// The future returned `Ok`.
+    Ready(T),
+    /// The future returned `Err`.
+    Err(E),
+}
+
+/// The result of a future spawned with [`Visitor::spawn`]. Like other state, it's kept in the
+/// variables captured by the render function and read in every render:
+/// ```
+/// let mut answer=edom::task::Task::<u32, String>::new();
+/// edom::noop::render(move |mut root| {
+///     if answer.is_idle() {
+///         root.spawn(&mut answer, async { Ok(42) });
+///     }
+///     match &*answer.state() {
+///         edom::task::TaskState::Ready(v)=>root.text(v.to_string().as_str()),
+///         edom::task::TaskState::Err(e)=>root.text(e.as_str()),
+///         _=>root.text("Loading..."),
+///     }
+/// });
+/// ```
+pub struct Task<T, E> {
+    state: Rc<RefCell<TaskState<T, E>>>,
+}
+
+impl<T, E> Default for Task<T, E> {
+    fn default()->Self {
+        Task { state: Rc::new(RefCell::new(TaskState::Idle)) }
+    }
+}
+
+impl<T, E> Task<T, E> {
+    /// Creates a task in [`TaskState::Idle`] state.
+    pub fn new()->Self {
+        Self::default()
+    }
+
+    /// The current state.
+    pub fn state(&self)->Ref<'_, TaskState<T, E>> {
+        self.state.borrow()
+    }
+
+    /// Whether nothing was spawned yet (or the result was taken).
+    pub fn is_idle(&self)->bool {
+        matches!(*self.state.borrow(), TaskState::Idle)
+    }
+
+    /// Whether the spawned future is still running.
+    pub fn is_pending(&self)->bool {
+        matches!(*self.state.borrow(), TaskState::Pending)
+    }
+
+    /// The value returned by the future if it finished successfully.
+    pub fn ready(&self)->Option<Ref<'_, T>> {
+        Ref::filter_map(self.state.borrow(), |s| match s {
+            TaskState::Ready(v)=>Some(v),
+            _=>None,
+        }).ok()
+    }
+
+    /// Returns the finished result and sets the state back to [`TaskState::Idle`].
+    /// A pending task is left running.
+    pub fn take(&mut self)->TaskState<T, E> {
+        if self.is_pending() {
+            return TaskState::Pending;
+        }
+        std::mem::replace(&mut *self.state.borrow_mut(), TaskState::Idle)
+    }
+}
+
+impl<'d, 'e, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Runs `future` with the executor of the backend and puts `task` in [`TaskState::Pending`] state.
+    /// When the future completes, its result is stored in `task` and the application is re-rendered.
+    /// Spawning again on the same task discards the result of the previous future.
+    pub fn spawn<T, E, Fut>(&self, task: &mut Task<T, E>, future: Fut)
+            where T: 'static, E: 'static, Fut: Future<Output=Result<T, E>> + 'static {
+        let state=Rc::new(RefCell::new(TaskState::Pending));
+        task.state=state.clone();
+        let rerender=self.edom.rerender.clone();
+        self.edom.executor.spawn_local(Box::pin(async move {
+            let result=future.await;
+            *state.borrow_mut()=match result {
+                Ok(v)=>TaskState::Ready(v),
+                Err(e)=>TaskState::Err(e),
+            };
+            // Nobody can see the result if the task was dropped or spawned again.
+            if Rc::strong_count(&state) > 1 {
+                rerender.borrow_mut()();
+            }
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::testing::TestApp;
+
+    /// The sent value and the waker of the task waiting for it.
+    type ChannelState = (Option<Result<u32, String>>, Option<Waker>);
+
+    /// A future that completes when the test sends a value.
+    #[derive(Clone, Default)]
+    struct Channel(Rc<RefCell<ChannelState>>);
+
+    impl Channel {
+        fn send(&self, v: Result<u32, String>) {
+            let mut c=self.0.borrow_mut();
+            c.0=Some(v);
+            if let Some(waker)=c.1.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    impl Future for Channel {
+        type Output = Result<u32, String>;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>)->Poll<Self::Output> {
+            let mut c=self.0.borrow_mut();
+            match c.0.take() {
+                Some(v)=>Poll::Ready(v),
+                None=>{
+                    c.1=Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    fn app(channels: Rc<RefCell<Vec<Channel>>>)->TestApp {
+        let mut task=Task::<u32, String>::new();
+        TestApp::new(move |mut root| {
+            if root.button("Load").clicked() {
+                let channel=Channel::default();
+                channels.borrow_mut().push(channel.clone());
+                root.spawn(&mut task, channel);
+            }
+            let text=match &*task.state() {
+                TaskState::Idle=>"idle".to_string(),
+                TaskState::Pending=>"loading".to_string(),
+                TaskState::Ready(v)=>v.to_string(),
+                TaskState::Err(e)=>e.clone(),
+            };
+            root.text(text.as_str());
+        })
+    }
+
+    #[test]
+    fn rerenders_when_ready() {
+        let channels=Rc::new(RefCell::new(Vec::new()));
+        let app=app(channels.clone());
+        let load=app.find_by_text("Load").unwrap();
+        assert_eq!("idle", app.body().text_content().replace("Load", ""));
+        app.click(&load);
+        assert_eq!(1, app.run_until_stalled());
+        assert_eq!("loading", app.body().text_content().replace("Load", ""));
+        channels.borrow()[0].send(Ok(42));
+        assert_eq!(0, app.run_until_stalled());
+        assert_eq!("42", app.body().text_content().replace("Load", ""));
+    }
+
+    #[test]
+    fn batched_rerender() {
+        let channels=Rc::new(RefCell::new(Vec::new()));
+        let app=app(channels.clone());
+        let frames=app.batch_renders();
+        app.click(&app.find_by_text("Load").unwrap());
+        assert_eq!(1, frames.run_frame());
+        app.run_until_stalled();
+        channels.borrow()[0].send(Ok(42));
+        app.run_until_stalled();
+        assert_eq!(1, frames.requested());
+        frames.run_frame();
+        assert_eq!("42", app.body().text_content().replace("Load", ""));
+    }
+
+    #[test]
+    fn spawning_again_discards_previous_result() {
+        let channels=Rc::new(RefCell::new(Vec::new()));
+        let app=app(channels.clone());
+        let load=app.find_by_text("Load").unwrap();
+        app.click(&load);
+        app.click(&load);
+        channels.borrow()[0].send(Ok(1));
+        app.run_until_stalled();
+        assert_eq!("loading", app.body().text_content().replace("Load", ""));
+        channels.borrow()[1].send(Err("failed".to_string()));
+        app.run_until_stalled();
+        assert_eq!("failed", app.body().text_content().replace("Load", ""));
+    }
+}
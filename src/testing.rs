@@ -0,0 +1,444 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::memdom;
+use super::vdom::{Element, Node, RenderIfState};
+use super::{Visitor, EDOM};
+
+/// A snapshot of a [`vdom::Element`](crate::vdom::Element) found by one of the `find_*` methods of [`TestApp`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestElement {
+    /// The uid that events are fired with.
+    pub uid: u64,
+    /// Tag name of the element.
+    pub tag: String,
+    /// Attributes as they were last rendered.
+    pub attributes: Vec<(String, String)>,
+    /// Concatenation of the direct text children of the element.
+    pub text: String,
+}
+
+impl TestElement {
+    fn new(element: &Element<memdom::ElementNode>)->Self {
+        let mut text=String::new();
+        for child in &element.children {
+            if let Node::Text(s, _)=child {
+                text.push_str(s);
+            }
+        }
+        TestElement {
+            uid: element.uid,
+            tag: element.name.to_string(),
+            attributes: element.attr.iter().map(|a| (a.0.to_string(), a.1.to_string())).collect(),
+            text,
+        }
+    }
+
+    /// Value of an attribute, `None` if it is not set.
+    pub fn attribute(&self, name: &str)->Option<&str> {
+        self.attributes.iter().find(|a| a.0 == name).map(|a| a.1.as_str())
+    }
+
+    fn has_class(&self, class: &str)->bool {
+        self.attribute("class").is_some_and(|c| c.split_whitespace().any(|c| c == class))
+    }
+}
+
+/// Mounts a render function on an in-memory [`memdom`] body and simulates user interaction
+/// with it, so widgets can be tested with `cargo test`:
+/// ```
+/// let mut count=0;
+/// let app=edom::testing::TestApp::new(move |mut root| {
+///     if root.button("Add").clicked() {
+///         count+=1;
+///     }
+///     root.text(count.to_string().as_str());
+/// });
+/// app.click(&app.find_by_text("Add").unwrap());
+/// assert_eq!("<button data-uid=\"1\">Add</button>1", app.html());
+/// ```
+pub struct TestApp {
+    edom: Rc<RefCell<EDOM<memdom::ElementNode>>>,
+    body: memdom::ElementNode,
+}
+
+impl TestApp {
+    /// Renders `f` the first time into a new `body` element.
+    pub fn new<F>(f: F)->Self where F: FnMut(Visitor<memdom::ElementNode>) + 'static {
+        let body: memdom::ElementNode=crate::dom::ElementNode::new("body");
+        let edom=memdom::run_and_fire_queued_events(|| EDOM::render(body.clone(), f));
+        TestApp { edom, body }
+    }
+
+    /// Runs `test` with a new application from `new_app` for each way of cloning `for_each` rows:
+    /// partially, from the DOM nodes of the visible elements only, and completely.
+    #[cfg(test)]
+    pub(crate) fn in_clone_modes<N, T>(mut new_app: N, mut test: T)
+            where N: FnMut()->TestApp, T: FnMut(TestApp) {
+        for use_partial_clone in [true, false] {
+            let app=new_app();
+            app.edom.borrow_mut().use_partial_clone=use_partial_clone;
+            test(app);
+        }
+    }
+
+    /// The body element that the application is rendered into.
+    pub fn body(&self)->&memdom::ElementNode {
+        &self.body
+    }
+
+    /// HTML of the rendered application (without the `body` tag).
+    pub fn html(&self)->String {
+        self.body.inner_html()
+    }
+
+    /// All elements of the vdom tree that are shown, in document order, for which `pred` returns true.
+    pub fn find_all<P>(&self, mut pred: P)->Vec<TestElement> where P: FnMut(&TestElement)->bool {
+        let mut r=Vec::new();
+        let edom=self.edom.borrow();
+        for child in &edom.get_root().children {
+            Self::collect(child, &mut pred, &mut r);
+        }
+        r
+    }
+
+    fn collect<P>(node: &Node<memdom::ElementNode>, pred: &mut P, r: &mut Vec<TestElement>)
+            where P: FnMut(&TestElement)->bool {
+        let element=match node {
+            Node::Element(e) | Node::Fragment(e)=>e,
+            Node::RenderIfElement(RenderIfState::Visible | RenderIfState::Leaving, e)=>e,
+            Node::ForEach(rows)=>{
+                for row in rows {
+                    Self::collect_element(&row.1, pred, r);
+                }
+                return;
+            },
+            _=>return,
+        };
+        Self::collect_element(element, pred, r);
+    }
+
+    fn collect_element<P>(element: &Element<memdom::ElementNode>, pred: &mut P, r: &mut Vec<TestElement>)
+            where P: FnMut(&TestElement)->bool {
+        let test_element=TestElement::new(element);
+        if !element.is_fragment() && pred(&test_element) {
+            r.push(test_element);
+        }
+        for child in &element.children {
+            Self::collect(child, pred, r);
+        }
+    }
+
+    /// The first element for which `pred` returns true.
+    pub fn find<P>(&self, pred: P)->Option<TestElement> where P: FnMut(&TestElement)->bool {
+        self.find_all(pred).into_iter().next()
+    }
+
+    /// The element with the given `id` attribute.
+    pub fn find_by_id(&self, id: &str)->Option<TestElement> {
+        self.find(|e| e.attribute("id") == Some(id))
+    }
+
+    /// The first element whose own text children equal `text`.
+    pub fn find_by_text(&self, text: &str)->Option<TestElement> {
+        self.find(|e| e.text == text)
+    }
+
+    /// All elements whose own text children equal `text`, in document order.
+    pub fn find_all_by_text(&self, text: &str)->Vec<TestElement> {
+        self.find_all(|e| e.text == text)
+    }
+
+    /// The first element with the given tag name.
+    pub fn find_by_tag(&self, tag: &str)->Option<TestElement> {
+        self.find(|e| e.tag == tag)
+    }
+
+    /// All elements with the given tag name.
+    pub fn find_all_by_tag(&self, tag: &str)->Vec<TestElement> {
+        self.find_all(|e| e.tag == tag)
+    }
+
+    /// The first element that has `class` between its classes.
+    pub fn find_by_class(&self, class: &str)->Option<TestElement> {
+        self.find(|e| e.has_class(class))
+    }
+
+    /// Runs the futures spawned by the application until none of them can make progress,
+    /// re-rendering when they complete. Returns the number of futures that are still pending.
+    pub fn run_until_stalled(&self)->usize {
+        let executor=self.edom.borrow().executor.clone();
+        executor.run_until_stalled()
+    }
+
+    /// Batches the renders of the application (see [`EDOM::set_scheduler`]) with a scheduler
+    /// whose frames are run by the test.
+    pub fn batch_renders(&self)->memdom::FrameScheduler {
+        let scheduler=memdom::FrameScheduler::new();
+        self.edom.borrow_mut().set_scheduler(Some(Rc::new(scheduler.clone())));
+        scheduler
+    }
+
+    /// Moves the fake clock forward, firing the timers created by [`Visitor::every`] and
+    /// [`Visitor::after`] that become due and re-rendering after each of them.
+    pub fn advance_time(&self, d: Duration) {
+        let clock=self.edom.borrow().event_handler.clock().clone();
+        clock.advance(d);
+    }
+
+    /// Number of timers that are still running.
+    pub fn pending_timers(&self)->usize {
+        self.edom.borrow().event_handler.clock().pending()
+    }
+
+    /// The DOM node of the element if it has any event listeners.
+    pub fn dnode(&self, element: &TestElement)->Option<memdom::ElementNode> {
+        Self::find_dnode(&self.body, element.uid.to_string().as_str())
+    }
+
+    fn find_dnode(dnode: &memdom::ElementNode, uid: &str)->Option<memdom::ElementNode> {
+        for child in dnode.child_elements() {
+            if child.attribute("data-uid").as_deref() == Some(uid) {
+                return Some(child);
+            }
+            if let Some(r)=Self::find_dnode(&child, uid) {
+                return Some(r);
+            }
+        }
+        None
+    }
+
+    /// Dispatches an event on the DOM node of `element`, rendering the application twice like the
+    /// browser event handler does. Returns `false` if the event handler called `prevent_default`.
+    /// Panics if the element doesn't listen to the event; see [`TestApp::try_fire`].
+    pub fn fire(&self, element: &TestElement, name: &str, event: memdom::Event)->bool {
+        self.try_fire(element, name, event)
+            .unwrap_or_else(|| panic!("No {} listener on <{}> {:?}", name, element.tag, element.attributes))
+    }
+
+    /// Same as [`TestApp::fire`], but returns `None` without firing if the element doesn't listen to
+    /// the event.
+    pub fn try_fire(&self, element: &TestElement, name: &str, event: memdom::Event)->Option<bool> {
+        let dnode=self.dnode(element)?;
+        if !dnode.listeners().iter().any(|l| l == name) {
+            return None;
+        }
+        Some(dnode.dispatch_event(name, event))
+    }
+
+    /// Focuses an element like the user would, firing `blur` on the focused element and `focus` on
+    /// this one.
+    pub fn focus(&self, element: &TestElement) {
+        if let Some(dnode)=self.dnode(element) {
+            crate::dom::ElementNode::focus(&dnode);
+        }
+    }
+
+    /// Removes the focus from an element, firing `blur` if it was focused.
+    pub fn blur(&self, element: &TestElement) {
+        if let Some(dnode)=self.dnode(element) {
+            dnode.blur();
+        }
+    }
+
+    /// Whether the element is the focused element.
+    pub fn has_focus(&self, element: &TestElement)->bool {
+        self.dnode(element).is_some_and(|dnode| dnode.has_focus())
+    }
+
+    /// Simulates a click.
+    pub fn click(&self, element: &TestElement)->bool {
+        self.fire(element, "click", memdom::Event::new())
+    }
+
+    /// Simulates a double click.
+    pub fn double_click(&self, element: &TestElement)->bool {
+        self.fire(element, "dblclick", memdom::Event::new())
+    }
+
+    /// Sets the value of an input element and fires an `input` event.
+    pub fn input(&self, element: &TestElement, value: &str)->bool {
+        if let Some(dnode)=self.dnode(element) {
+            crate::dom::ElementNode::set_attribute(&dnode, "value", value);
+        }
+        self.fire(element, "input", memdom::Event::new())
+    }
+
+    /// Replaces the text or the inner HTML of a content editable element like typing would, and fires
+    /// an `input` event.
+    pub fn edit_content(&self, element: &TestElement, content: &str, mode: crate::vdom::ContentMode)->bool {
+        if let Some(dnode)=self.dnode(element) {
+            match mode {
+                crate::vdom::ContentMode::Text=>crate::dom::ElementNode::set_inner_text(&dnode, content),
+                crate::vdom::ContentMode::Html=>crate::dom::ElementNode::set_inner_html(&dnode, content),
+            }
+        }
+        self.fire(element, "input", memdom::Event::new())
+    }
+
+    /// Selects the option at `index` of a `select` element (deselecting the others) and fires a
+    /// `change` event.
+    pub fn select_option(&self, select: &TestElement, index: usize)->bool {
+        if let Some(dnode)=self.dnode(select) {
+            for (i, option) in dnode.child_elements().iter().enumerate() {
+                crate::dom::ElementNode::set_attribute(option, "selected", (i == index).to_string().as_str());
+            }
+        }
+        self.fire(select, "change", memdom::Event::new())
+    }
+
+    /// Toggles the selection of the option at `index` of a `select` element with the `multiple`
+    /// attribute and fires a `change` event.
+    pub fn toggle_option(&self, select: &TestElement, index: usize)->bool {
+        if let Some(option)=self.dnode(select).and_then(|dnode| dnode.child_elements().into_iter().nth(index)) {
+            let selected=option.attribute("selected").as_deref() == Some("true");
+            crate::dom::ElementNode::set_attribute(&option, "selected", (!selected).to_string().as_str());
+        }
+        self.fire(select, "change", memdom::Event::new())
+    }
+
+    /// Fires a `submit` event on a form.
+    pub fn submit(&self, element: &TestElement)->bool {
+        self.fire(element, "submit", memdom::Event::new())
+    }
+
+    /// Fires a `keydown` event with the given key value (like `"Enter"` or `"a"`).
+    pub fn keydown(&self, element: &TestElement, key: &str)->bool {
+        self.fire(element, "keydown", memdom::Event::with_key(key))
+    }
+
+    /// Fires a mouse, pointer or wheel event like `"pointerenter"` or `"wheel"`.
+    pub fn pointer(&self, element: &TestElement, name: &str, event: crate::pointer::PointerEvent)->bool {
+        self.fire(element, name, memdom::Event::with_pointer(event))
+    }
+
+    /// Simulates a right click, which requests the context menu.
+    pub fn right_click(&self, element: &TestElement)->bool {
+        let event=crate::pointer::PointerEvent::default().with_button(crate::pointer::MouseButton::Secondary);
+        self.fire(element, "contextmenu", memdom::Event::with_pointer(event))
+    }
+
+    /// Drags `from` and drops it on `to` with the events of HTML5 drag and drop.
+    pub fn drag(&self, from: &TestElement, to: &TestElement)->bool {
+        self.fire(from, "dragstart", memdom::Event::new());
+        self.fire(to, "dragover", memdom::Event::new());
+        let r=self.fire(to, "drop", memdom::Event::new());
+        self.fire(from, "dragend", memdom::Event::new());
+        r
+    }
+
+    /// Fires a `keydown` event with modifiers, code or repeat set.
+    pub fn keydown_with(&self, element: &TestElement, event: crate::keyboard::KeyboardEvent)->bool {
+        self.fire(element, "keydown", memdom::Event::with_keyboard(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo_app()->TestApp {
+        let mut todos: Vec<(u64, String)>=Vec::new();
+        let mut new_text=String::new();
+        let mut next_id=0;
+        TestApp::new(move |mut root| {
+            root.form(|form| {
+                form.text_input(&mut new_text).id("new");
+                if form.on_submit() {
+                    todos.push((next_id, new_text.clone()));
+                    next_id+=1;
+                    new_text.clear();
+                }
+            });
+            let mut remove=None;
+            root.ul(|ul| {
+                ul.for_each(todos.iter_mut(), |t| t.0, "li", |t, li| {
+                    li.text(t.1.as_str());
+                    if li.button("x").class("destroy").clicked() {
+                        remove=Some(t.0);
+                    }
+                });
+            });
+            if let Some(id)=remove {
+                todos.retain(|t| t.0 != id);
+            }
+        })
+    }
+
+    #[test]
+    fn add_and_remove_items() {
+        let app=todo_app();
+        let input=app.find_by_id("new").unwrap();
+        let form=app.find_by_tag("form").unwrap();
+        for text in ["a", "b", "c"] {
+            app.input(&input, text);
+            assert!(!app.submit(&form));
+        }
+        assert_eq!(vec!["a", "b", "c"], app.find_all_by_tag("li").iter().map(|li| li.text.clone())
+            .collect::<Vec<_>>());
+        let destroy=app.find_all(|e| e.has_class("destroy"));
+        app.click(&destroy[1]);
+        let ul=app.dnode(&destroy[0]).unwrap().parent().unwrap().parent().unwrap();
+        assert_eq!(vec!["ax", "cx"], ul.child_elements().iter().map(|li| li.text_content())
+            .collect::<Vec<_>>());
+        assert_eq!("", app.find_by_id("new").unwrap().attribute("value").unwrap());
+    }
+
+    #[test]
+    fn keydown_and_double_click() {
+        let mut log=String::new();
+        let app=TestApp::new(move |mut root| {
+            root.span(|span| {
+                span.on("keydown", |e| log.push_str(&e.key().unwrap()));
+                if span.double_clicked() {
+                    log.push('!');
+                }
+            });
+            root.text(log.as_str());
+        });
+        let span=app.find_by_tag("span").unwrap();
+        app.keydown(&span, "a");
+        app.double_click(&span);
+        app.keydown(&span, "b");
+        assert_eq!("a!b", app.body().children()[1].text_content());
+    }
+
+    #[test]
+    fn try_fire_without_listener() {
+        let app=todo_app();
+        let input=app.find_by_id("new").unwrap();
+        assert_eq!(None, app.try_fire(&input, "click", memdom::Event::new()));
+        assert_eq!(Some(false), app.try_fire(&app.find_by_tag("form").unwrap(), "submit", memdom::Event::new()));
+    }
+
+    #[test]
+    #[should_panic(expected="No click listener on <input>")]
+    fn fire_without_listener() {
+        let app=todo_app();
+        app.click(&app.find_by_id("new").unwrap());
+    }
+
+    #[test]
+    fn batched_renders() {
+        let renders=Rc::new(std::cell::Cell::new(0));
+        let moved_renders=renders.clone();
+        let mut text=String::new();
+        let app=TestApp::new(move |mut root| {
+            moved_renders.set(moved_renders.get() + 1);
+            root.text_input(&mut text).id("text");
+            root.text(text.len().to_string().as_str());
+        });
+        let frames=app.batch_renders();
+        let input=app.find_by_id("text").unwrap();
+        for value in ["a", "ab", "abc"] {
+            app.input(&input, value);
+        }
+        assert_eq!(4, renders.get());
+        assert_eq!(1, frames.requested());
+        assert_eq!(1, frames.run_frame());
+        assert_eq!(5, renders.get());
+        assert_eq!("3", app.body().children()[1].text_content());
+        assert_eq!(0, frames.run_frame());
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use super::dom;
+use super::visitor::Visitor;
+
+impl<'d, 'e, 'c, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Returns true in the render pass that is caused by the timer of the current element firing,
+    /// which happens in every `interval`:
+    /// ```
+    /// let mut seconds=0;
+    /// let app=edom::testing::TestApp::new(move |mut root| {
+    ///     root.div(|div| {
+    ///         if div.every(std::time::Duration::from_secs(1)) {
+    ///             seconds+=1;
+    ///         }
+    ///         div.text(seconds.to_string().as_str());
+    ///     });
+    /// });
+    /// app.advance_time(std::time::Duration::from_millis(2500));
+    /// assert_eq!("<div>2</div>", app.html());
+    /// ```
+    /// The timer is started when the element is created and cancelled when it's removed from the
+    /// vdom. It's also cancelled while the element is hidden by `render_element_if`, and started
+    /// again with the full interval when it's shown.
+    pub fn every(&'c mut self, interval: Duration)->bool {
+        self.timer(interval, true)
+    }
+
+    /// Like [`Visitor::every`], but fires only once, `delay` after the element was created.
+    pub fn after(&'c mut self, delay: Duration)->bool {
+        self.timer(delay, false)
+    }
+
+    fn timer(&'c mut self, interval: Duration, repeat: bool)->bool {
+        if self.edom.create {
+            self.element.add_timer(interval, repeat, self.edom);
+        }
+        let uid=self.element.extras_mut().timer_registrations[self.timerpos].uid;
+        self.timerpos+=1;
+        self.edom.firing_event.as_ref().is_some_and(|e| e.0 == uid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestApp;
+
+    #[test]
+    fn toast_is_dismissed() {
+        let mut toasts: Vec<(u64, String)>=Vec::new();
+        let mut next_id=0;
+        let app=TestApp::new(move |mut root| {
+            if root.button("Save").clicked() {
+                toasts.push((next_id, format!("Saved {}", next_id)));
+                next_id+=1;
+            }
+            let mut dismissed=None;
+            root.for_each(toasts.iter_mut(), |t| t.0, "p", |t, p| {
+                p.text(t.1.as_str());
+                if p.after(Duration::from_secs(3)) {
+                    dismissed=Some(t.0);
+                }
+            });
+            if let Some(id)=dismissed {
+                toasts.retain(|t| t.0 != id);
+            }
+        });
+        let save=app.find_by_text("Save").unwrap();
+        app.click(&save);
+        app.advance_time(Duration::from_secs(2));
+        app.click(&save);
+        assert_eq!(2, app.find_all_by_tag("p").len());
+        app.advance_time(Duration::from_secs(1));
+        assert_eq!(vec!["Saved 1"], app.find_all_by_tag("p").iter().map(|p| p.text.clone())
+            .collect::<Vec<_>>());
+        app.advance_time(Duration::from_secs(10));
+        assert!(app.find_by_tag("p").is_none());
+        assert_eq!(0, app.pending_timers());
+    }
+
+    #[test]
+    fn events_are_not_timers() {
+        let (mut events, mut fired)=(0, false);
+        let app=TestApp::new(move |mut root| {
+            root.div(|div| {
+                div.id("div");
+                div.on("edom:every:10", |_| events+=1);
+                if div.after(Duration::from_millis(50)) {
+                    fired=true;
+                }
+                div.text(format!("{} {}", events, fired).as_str());
+            });
+        });
+        assert_eq!(1, app.pending_timers());
+        app.advance_time(Duration::from_millis(100));
+        assert_eq!("<div id=\"div\" data-uid=\"1\">0 true</div>", app.html());
+        app.fire(&app.find_by_id("div").unwrap(), "edom:every:10", crate::memdom::Event::new());
+        assert!(app.html().ends_with(">1 true</div>"));
+    }
+
+    #[test]
+    fn stopped_while_hidden() {
+        let renders=std::rc::Rc::new(std::cell::Cell::new(0));
+        let moved_renders=renders.clone();
+        let (mut shown, mut polls)=(true, 0);
+        let app=TestApp::new(move |mut root| {
+            moved_renders.set(moved_renders.get() + 1);
+            if root.button("toggle").clicked() {
+                shown=!shown;
+            }
+            root.render_element_if(shown, "div", |div| {
+                div.span(|span| {
+                    if span.every(Duration::from_secs(1)) {
+                        polls+=1;
+                    }
+                });
+                div.text(polls.to_string().as_str());
+            });
+        });
+        let toggle=|| app.click(&app.find_by_text("toggle").unwrap());
+        app.advance_time(Duration::from_millis(1500));
+        toggle();
+        assert_eq!(0, app.pending_timers());
+        let n=renders.get();
+        app.advance_time(Duration::from_secs(10));
+        assert_eq!(n, renders.get());
+        // Shown again, the timer starts from the beginning.
+        toggle();
+        assert_eq!(1, app.pending_timers());
+        app.advance_time(Duration::from_millis(999));
+        assert!(app.html().ends_with("<span></span>1</div>"));
+        app.advance_time(Duration::from_millis(1));
+        assert!(app.html().ends_with("<span></span>2</div>"));
+    }
+}
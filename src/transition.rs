@@ -0,0 +1,278 @@
+use std::time::Duration;
+
+use super::dom;
+use super::vdom::Element;
+use super::visitor::Visitor;
+use super::EDOM;
+
+const FRAME_EVENT: &str = "edom:transition:frame";
+const END_EVENT: &str = "edom:transition:end";
+
+/// How an element of [`Visitor::render_element_if_with`] appears and disappears.
+///
+/// With a class name `name`, the element gets the `name-enter` and `name-enter-active` classes when
+/// it's inserted, `name-enter` is replaced by `name-enter-to` in the next frame, and both are removed
+/// when the enter transition ends. Leaving works the same way with `name-leave`, `name-leave-active`
+/// and `name-leave-to`, and the element is removed from the DOM only after the leave transition:
+/// ```css
+/// .fade-enter-active, .fade-leave-active { transition: opacity 0.3s; }
+/// .fade-enter, .fade-leave-to { opacity: 0; }
+/// ```
+/// [`Transition::fade`] and [`Transition::slide`] do the same with inline styles, without CSS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    name: Option<&'static str>,
+    hidden_style: Option<&'static str>,
+    enter: Duration,
+    leave: Duration,
+}
+
+impl Transition {
+    /// A transition that sets the CSS classes starting with `name`, taking `duration` both ways.
+    pub fn classes(name: &'static str, duration: Duration)->Self {
+        Transition { name: Some(name), hidden_style: None, enter: duration, leave: duration }
+    }
+
+    /// Fades the element in and out by changing its opacity.
+    pub fn fade(duration: Duration)->Self {
+        Transition { name: None, hidden_style: Some("opacity: 0"), enter: duration, leave: duration }
+    }
+
+    /// Slides the element down when it appears and up when it disappears.
+    pub fn slide(duration: Duration)->Self {
+        Transition { name: None, hidden_style: Some("transform: scaleY(0); transform-origin: top"),
+            enter: duration, leave: duration }
+    }
+
+    /// Sets a different duration for entering.
+    pub fn enter_duration(mut self, duration: Duration)->Self {
+        self.enter=duration;
+        self
+    }
+
+    /// Sets a different duration for leaving.
+    pub fn leave_duration(mut self, duration: Duration)->Self {
+        self.leave=duration;
+        self
+    }
+
+    fn class<EN: dom::ElementNode>(&self, element: &mut Element<EN>, dnode: &EN, suffix: &str, add: bool) {
+        if let Some(name)=self.name {
+            set_token(element, dnode, "class", format!("{}-{}", name, suffix), add);
+        }
+    }
+
+    fn hidden<EN: dom::ElementNode>(&self, element: &mut Element<EN>, dnode: &EN, add: bool) {
+        if let Some(style)=self.hidden_style {
+            set_token(element, dnode, "style", style.to_string(), add);
+        }
+    }
+
+    fn transition_style<EN: dom::ElementNode>(&self, element: &mut Element<EN>, dnode: &EN, duration: Duration, add: bool) {
+        if self.hidden_style.is_some() {
+            set_token(element, dnode, "style", format!("transition: all {}ms", duration.as_millis()), add);
+        }
+    }
+
+    fn start_timers<EN>(&self, edom: &EDOM<EN>, element: &mut Element<EN>, duration: Duration)
+            where EN: dom::ElementNode {
+        let (event_handler, uid)=(&edom.event_handler, element.uid);
+        element.extras_mut().transition_timers=vec![
+            dom::EventHandler::create_timer(event_handler, uid, FRAME_EVENT.to_string(), crate::ANIMATION_FRAME, false),
+            dom::EventHandler::create_timer(event_handler, uid, END_EVENT.to_string(), duration, false),
+        ];
+    }
+
+    /// Removes everything that was added by the enter or leave transition.
+    fn clear<EN>(&self, element: &mut Element<EN>, dnode: &EN) where EN: dom::ElementNode {
+        element.extras_mut().transition_timers.clear();
+        for suffix in ["enter", "enter-active", "enter-to", "leave", "leave-active", "leave-to"] {
+            self.class(element, dnode, suffix, false);
+        }
+        self.hidden(element, dnode, false);
+        self.transition_style(element, dnode, self.enter, false);
+        self.transition_style(element, dnode, self.leave, false);
+    }
+
+    pub(crate) fn start_enter<EN>(&self, edom: &EDOM<EN>, element: &mut Element<EN>, dnode: &EN)
+            where EN: dom::ElementNode {
+        self.clear(element, dnode);
+        self.class(element, dnode, "enter", true);
+        self.class(element, dnode, "enter-active", true);
+        self.hidden(element, dnode, true);
+        self.transition_style(element, dnode, self.enter, true);
+        self.start_timers(edom, element, self.enter);
+    }
+
+    pub(crate) fn start_leave<EN>(&self, edom: &EDOM<EN>, element: &mut Element<EN>, dnode: &EN)
+            where EN: dom::ElementNode {
+        self.clear(element, dnode);
+        self.class(element, dnode, "leave", true);
+        self.class(element, dnode, "leave-active", true);
+        self.transition_style(element, dnode, self.leave, true);
+        self.start_timers(edom, element, self.leave);
+    }
+
+    /// Moves the transition to its next phase if one of its timers fired.
+    /// Returns true when a leave transition ended.
+    pub(crate) fn on_timer<EN>(&self, edom: &EDOM<EN>, element: &mut Element<EN>, dnode: &EN, leaving: bool)->bool
+            where EN: dom::ElementNode {
+        let Some((uid, name, _))=&edom.firing_event else { return false };
+        if *uid != element.uid {
+            return false;
+        }
+        let (from, to)=if leaving { ("leave", "leave-to") } else { ("enter", "enter-to") };
+        if name == FRAME_EVENT {
+            self.class(element, dnode, from, false);
+            self.class(element, dnode, to, true);
+            // The hidden style is the start of entering and the end of leaving.
+            self.hidden(element, dnode, leaving);
+        } else if name == END_EVENT {
+            self.clear(element, dnode);
+            return leaving;
+        }
+        false
+    }
+}
+
+/// Adds or removes `token` in the transition tokens of `element` that are in the class list or in the
+/// declarations of the style attribute, and writes the attribute with the tokens to the DOM.
+fn set_token<EN: dom::ElementNode>(element: &mut Element<EN>, dnode: &EN, attribute: &'static str, token: String, add: bool) {
+    let tokens=&mut element.extras_mut().transition_tokens;
+    tokens.retain(|t| t.0 != attribute || t.1 != token);
+    if add {
+        tokens.push((attribute, token));
+    }
+    let value=element.with_transition_tokens(attribute, element.attribute(attribute).unwrap_or(""));
+    if dnode.get_attribute(attribute) != value {
+        dnode.set_attribute(attribute, &value);
+    }
+}
+
+impl<'d, 'e, 'f, EN> Visitor<'d, 'e, EN> where EN: dom::ElementNode {
+    /// Like [`Visitor::render_element_if`], but the element appears and disappears with `transition`.
+    /// While the leave transition runs, the element stays in the DOM in
+    /// [`RenderIfState::Leaving`](crate::vdom::RenderIfState::Leaving) state and is not visited.
+    pub fn render_element_if_with<FCB>(&'f mut self, should_render: bool, tag: impl Into<dom::Name>,
+            transition: &Transition, fcb: FCB) where FCB: FnMut(&mut Visitor<EN>) {
+        self.render_element_if_impl(should_render, tag.into(), Some(transition), fcb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestApp;
+
+    fn ms(ms: u64)->Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn app(transition: Transition)->TestApp {
+        let mut open=false;
+        TestApp::new(move |mut root| {
+            if root.button("Toggle").clicked() {
+                open=!open;
+            }
+            root.render_element_if_with(open, "p", &transition, |p| {
+                p.class("message").text("Hello");
+            });
+            root.text("end");
+        })
+    }
+
+    #[test]
+    fn classes() {
+        let app=app(Transition::classes("fade", ms(300)));
+        let toggle=app.find_by_text("Toggle").unwrap();
+        let html=|| app.html().replace("<button data-uid=\"1\">Toggle</button>", "");
+        app.click(&toggle);
+        assert_eq!("<p class=\"message fade-enter fade-enter-active\">Hello</p>end", html());
+        app.advance_time(ms(16));
+        assert_eq!("<p class=\"message fade-enter-active fade-enter-to\">Hello</p>end", html());
+        app.advance_time(ms(300));
+        assert_eq!("<p class=\"message\">Hello</p>end", html());
+        app.click(&toggle);
+        assert_eq!("<p class=\"message fade-leave fade-leave-active\">Hello</p>end", html());
+        app.advance_time(ms(16));
+        assert_eq!("<p class=\"message fade-leave-active fade-leave-to\">Hello</p>end", html());
+        app.advance_time(ms(300));
+        assert_eq!("end", html());
+        app.click(&toggle);
+        assert_eq!("<p class=\"message fade-enter fade-enter-active\">Hello</p>end", html());
+        assert_eq!(2, app.pending_timers());
+    }
+
+    #[test]
+    fn reenter_while_leaving() {
+        let app=app(Transition::fade(ms(100)));
+        let toggle=app.find_by_text("Toggle").unwrap();
+        app.click(&toggle);
+        app.advance_time(ms(200));
+        app.click(&toggle);
+        app.advance_time(ms(16));
+        assert_eq!(Some("transition: all 100ms; opacity: 0".to_string()),
+            app.body().child_elements()[1].attribute("style"));
+        app.click(&toggle);
+        assert_eq!(Some("opacity: 0; transition: all 100ms".to_string()),
+            app.body().child_elements()[1].attribute("style"));
+        app.advance_time(ms(200));
+        assert_eq!("Helloend", app.body().text_content().replace("Toggle", ""));
+        assert_eq!(Some(String::new()), app.body().child_elements()[1].attribute("style"));
+        assert_eq!(0, app.pending_timers());
+    }
+
+    #[test]
+    fn class_changed_while_entering() {
+        let mut open=false;
+        let mut important=false;
+        let app=TestApp::new(move |mut root| {
+            if root.button("Toggle").clicked() {
+                open=!open;
+            }
+            if root.button("Important").clicked() {
+                important=!important;
+            }
+            root.render_element_if_with(open, "p", &Transition::classes("fade", ms(100)), |p| {
+                p.classes(&[("message", true), ("important", important)]).style("color: red").text("Hello");
+            });
+        });
+        let p=|| app.body().child_elements()[2].clone();
+        app.click(&app.find_by_text("Toggle").unwrap());
+        app.advance_time(ms(16));
+        app.click(&app.find_by_text("Important").unwrap());
+        assert_eq!(Some("message important fade-enter-active fade-enter-to".to_string()), p().attribute("class"));
+        app.advance_time(ms(100));
+        assert_eq!(Some("message important".to_string()), p().attribute("class"));
+        assert_eq!(Some("color: red".to_string()), p().attribute("style"));
+    }
+
+    #[test]
+    fn row_cloned_while_leaving() {
+        TestApp::in_clone_modes(|| {
+            let mut rows=vec![(0, true)];
+            TestApp::new(move |mut root| {
+                if root.button("Hide").clicked() {
+                    rows[0].1=false;
+                }
+                if root.button("Add").clicked() {
+                    rows.push((rows.len(), true));
+                }
+                root.ul(|ul| ul.for_each(rows.iter(), |r| r.0, "li", |r, li| {
+                    li.render_element_if_with(r.1, "p", &Transition::fade(ms(100)), |p| {
+                        p.text(r.0.to_string().as_str());
+                    });
+                }));
+            })
+        }, |app| {
+            app.advance_time(ms(200));
+            app.click(&app.find_by_text("Hide").unwrap());
+            app.advance_time(ms(50));
+            // The new row is cloned from the first one, whose paragraph is leaving.
+            app.click(&app.find_by_text("Add").unwrap());
+            app.advance_time(ms(200));
+            assert_eq!("<li></li><li><p style=\"\">1</p></li>", app.body().child_elements()[2].inner_html());
+            assert_eq!(0, app.pending_timers());
+        });
+    }
+}
@@ -1,12 +1,15 @@
 #[cfg(not(doctest))]
 use std::fmt;
 use std::rc::Rc;
+use std::cell::RefCell;
+use std::time::Duration;
 
 use crate::ElementNode;
 use crate::dom::EventHandler;
 use crate::dom::GenericNode;
 
 use super::dom;
+use super::dom::Name;
 use super::EDOM;
 use super::visitor::Visitor;
 pub enum Node<EN> where EN:dom::ElementNode {
@@ -26,7 +29,10 @@ pub enum Node<EN> where EN:dom::ElementNode {
     ///  ));
     /// ```
     ForEach(Vec<(u64, Element<EN>)>),
-    RenderIfElement(RenderIfState, Element<EN>)
+    RenderIfElement(RenderIfState, Element<EN>),
+    /// Children without a wrapper element, created by [`Visitor::fragment`]. The element of the
+    /// fragment has no tag, its DOM nodes are children of the DOM node of the parent.
+    Fragment(Element<EN>)
 }
 
 impl<EN:dom::ElementNode> Node<EN> {
@@ -34,17 +40,32 @@ impl<EN:dom::ElementNode> Node<EN> {
         let Node::Text(s, _)=self else {panic!("Not text")};
         return s
     }
+
+    /// Number of DOM nodes of the node in the DOM node of its parent.
+    pub fn dom_len(&self)->usize {
+        match self {
+            Node::Text(..)=>1,
+            Node::Element(e) | Node::Fragment(e)=>e.dom_len(),
+            Node::RenderIfElement(RenderIfState::Visible | RenderIfState::Leaving, e)=>e.dom_len(),
+            Node::RenderIfElement(..)=>0,
+            Node::ForEach(rows)=>rows.iter().map(|row| row.1.dom_len()).sum(),
+        }
+    }
 }
 
 /// [`Visitor::render_element_if`] function is doing conditional rendering with a callback.
 /// First if rendering is turned off, the element is in [`RenderIfState::NotRendered`] state.
 /// Then it gets to [`RenderIfState::Visible`], after that [`RenderIfState::Hidden`]
 ///  (which means that the DOM nodes are kept).
+/// With a [`crate::transition::Transition`] it's [`RenderIfState::Leaving`] between
+///  `Visible` and `Hidden` while the element is still in the DOM.
 #[derive(Clone, PartialEq)]
 pub enum RenderIfState {
     NotRendered,
     Hidden,
-    Visible
+    Visible,
+    /// The leave transition is running, the element is removed from the DOM when it ends.
+    Leaving
 }
 
 impl<EN:dom::ElementNode> Node<EN> {
@@ -54,6 +75,27 @@ impl<EN:dom::ElementNode> Node<EN> {
     }
 }
 
+/// How the content of a [`Visitor::content_editable`] element is bound to a string.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContentMode {
+    /// The string is the inner text of the element, with `\n` for the line breaks.
+    Text,
+    /// The string is the inner HTML of the element.
+    Html,
+}
+
+/// A timer of [`Visitor::every`] or [`Visitor::after`]. It fires with its own uid, so it can't be
+/// confused with an event of the element.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimerRegistration {
+    /// The uid of the events fired by the timer.
+    pub uid: u64,
+    /// Time until the first event, and between the events if it repeats.
+    pub interval: Duration,
+    /// Whether the timer fires in every `interval` or only once.
+    pub repeat: bool,
+}
+
 pub struct CachedValue<T> {
     value: std::cell::UnsafeCell<Option<T>>
 }
@@ -82,64 +124,288 @@ impl<T> CachedValue<T> {
 }
 
 pub struct Element<EN> where EN:dom::ElementNode {
-    pub name: &'static str,
-    pub attr: Vec<(&'static str,Rc<String>)>,
+    pub name: Name,
+    pub attr: Vec<(Name,Rc<String>)>,
     pub children: Vec<Node<EN>>,
     pub dnode: CachedValue<EN>,
-    pub events: Vec<&'static str>,
+    pub events: Vec<Name>,
     pub uid: u64,
+    /// The fields that most elements don't use, `None` until one of them is set.
+    pub extras: Option<Box<ElementExtras<EN>>>,
+}
+
+/// The rarely used fields of an [`Element`], allocated by [`Element::extras_mut`].
+pub struct ElementExtras<EN> where EN:dom::ElementNode {
+    /// Timers of the element in the order of the [`Visitor::every`] and [`Visitor::after`] calls.
+    pub timer_registrations: Vec<TimerRegistration>,
+    /// The running timers of [`ElementExtras::timer_registrations`], cancelled when the element is dropped.
+    pub timers: RefCell<Vec<<EN::EventHandler as dom::EventHandler>::Timer>>,
+    /// Timers of the running enter or leave transition of a render if element.
+    pub transition_timers: Vec<<EN::EventHandler as dom::EventHandler>::Timer>,
+    /// Classes and style declarations of the running transition as `(attribute, token)` pairs,
+    /// added to the `class` and `style` values of [`Element::attr`] in the DOM.
+    pub transition_tokens: Vec<(&'static str, String)>,
+    /// The content of a content editable element as it was last written to or read from the DOM.
+    /// The children of such elements are not in the vdom.
+    pub content: Option<(ContentMode, Rc<String>)>,
+    /// Position of the pointer over the element, tracked by [`Visitor::hovered`] and [`Visitor::mouse_pos`].
+    pub pointer_position: Option<(f64, f64)>,
+    /// Values of [`Visitor::state`] in the order of the calls.
+    pub state: Vec<Box<dyn std::any::Any>>,
+    /// Hash of the key of [`Visitor::memo`] when the children were last rendered.
+    pub memo_key: Option<u64>,
+    /// Set for elements created by [`Element::fragment`].
+    pub fragment: bool,
+}
+
+impl<EN> Default for ElementExtras<EN> where EN:dom::ElementNode {
+    fn default()->Self {
+        Self {timer_registrations: Vec::new(), timers: RefCell::new(Vec::new()), transition_timers: Vec::new(),
+            transition_tokens: Vec::new(), content: None, pointer_position: None, state: Vec::new(), memo_key: None,
+            fragment: false}
+    }
 }
 
 impl<EN> Element<EN>  where EN:dom::ElementNode {
-    pub fn new(name: &'static str, dnode: Option<EN>, uid: u64)->Self {
-        Self {name, attr:vec![], children: vec![], dnode: CachedValue::new(dnode), events: Vec::new(), uid}
+    pub fn new(name: impl Into<Name>, dnode: Option<EN>, uid: u64)->Self {
+        Self {name: name.into(), attr:vec![], children: vec![], dnode: CachedValue::new(dnode), events: Vec::new(), uid,
+            extras: None}
+    }
+    /// An element without tag for the children of a fragment that are in `parent_dnode`.
+    pub fn fragment(parent_dnode: EN, uid: u64)->Self {
+        let mut r=Self::new("", Some(parent_dnode), uid);
+        r.extras_mut().fragment=true;
+        r
+    }
+    /// The [`Element::extras`] of the element, allocating them if it has none.
+    pub fn extras_mut(&mut self)->&mut ElementExtras<EN> {
+        self.extras.get_or_insert_with(Default::default)
+    }
+    /// Whether the element is a fragment, which has no tag and no DOM node of its own.
+    pub fn is_fragment(&self)->bool {
+        self.extras.as_ref().is_some_and(|x| x.fragment)
     }
-    pub fn create_event_listener(&self, name: &'static str, edom: &EDOM<EN>, dnode: &EN) {
+    /// The content of a content editable element, see [`ElementExtras::content`].
+    pub fn content(&self)->Option<&(ContentMode, Rc<String>)> {
+        self.extras.as_ref().and_then(|x| x.content.as_ref())
+    }
+    /// Number of DOM nodes of the element in the DOM node of its parent: 1, or the number of DOM
+    /// nodes of the children for fragments.
+    pub fn dom_len(&self)->usize {
+        if self.is_fragment() {
+            self.children.iter().map(|child| child.dom_len()).sum()
+        } else {
+            1
+        }
+    }
+    pub fn create_event_listener(&self, name: &str, edom: &EDOM<EN>, dnode: &EN) {
         dnode.set_attribute("data-uid", self.uid.to_string().as_str());
         edom.event_handler.create_event_listener(dnode, name.to_string());
         // dnode.create_dnode_event_listener(edom.fire_event.clone(), self.uid, name);
     }
+    /// Registers a timer that fires after `interval`, and after that in every `interval` if `repeat` is set.
+    pub fn add_timer(&mut self, interval: Duration, repeat: bool, edom: &mut EDOM<EN>) {
+        let uid=edom.next_uid();
+        self.extras_mut().timer_registrations.push(TimerRegistration {uid, interval, repeat});
+        self.start_timers(edom);
+    }
+    /// Starts the registered timers that are not running.
+    pub fn start_timers(&self, edom: &EDOM<EN>) {
+        let Some(extras)=&self.extras else { return };
+        let mut timers=extras.timers.borrow_mut();
+        for t in &extras.timer_registrations[timers.len()..] {
+            timers.push(edom.event_handler.create_timer(t.uid, "timer".to_string(), t.interval, t.repeat));
+        }
+    }
     pub fn clone_using_dnode(&self, target_dnode: EN, edom: &mut EDOM<EN>)->Self {
-        let mut r = Self {name: self.name, attr: self.attr.clone(), children: Vec::new(), dnode: CachedValue::new(Some(target_dnode)), events: self.events.clone(), uid: edom.next_uid()};
+        let mut r = self.shallow_clone(Some(target_dnode), edom);
         let rdnode=r.dnode.get(|| panic!("Should exist"));
 
         for event_name in &self.events {
-            self.create_event_listener(event_name, edom, rdnode);
+            r.create_event_listener(event_name, edom, rdnode);
         }
+        r.start_timers(edom);
 
-        let mut next_child_idx=0;
-        let new_dchildren=rdnode.get_child_nodes();
-        for new_dchild in new_dchildren.into_iter() {
-            let child = &self.children[next_child_idx];
-            match child {
-                Node::Text(s, _)=>{
-                    r.children.push(Node::Text(s.clone(), Some(new_dchild.into_text_node())));
-                },
-                Node::Element(e)=>{
-                    r.children.push(Node::Element(e.clone_using_dnode(new_dchild.into_element_node(), edom)));
+        if self.content().is_some() {
+            return r;
+        }
+        let mut new_dchildren=rdnode.get_child_nodes().into_iter();
+        self.clone_children_using_dnodes(&mut r, rdnode, &mut new_dchildren, edom);
+        r
+    }
+
+    /// Clones the children to `r`, using the next DOM nodes of `dchildren`, which are the children of `dnode`.
+    fn clone_children_using_dnodes(&self, r: &mut Self, dnode: &EN,
+            dchildren: &mut std::vec::IntoIter<EN::GenericNode>, edom: &mut EDOM<EN>) {
+        for child in &self.children {
+            let new_child=match child {
+                Node::Text(s, _)=>Node::Text(s.clone(), Some(Self::next_dchild(dchildren).into_text_node())),
+                Node::Element(e)=>Node::Element(e.clone_in(dnode, dchildren, edom)),
+                Node::RenderIfElement(state, e)=>{
+                    if *state==RenderIfState::Visible {
+                        Node::RenderIfElement(RenderIfState::Visible, e.clone_in(dnode, dchildren, edom))
+                    } else if *state==RenderIfState::Leaving {
+                        // The element is removed from the DOM when the leave transition ends, so the clone is hidden.
+                        let new_elem=e.clone_in(dnode, dchildren, edom);
+                        dnode.remove_child(new_elem.dnode.unwrap());
+                        new_elem.stop_timers();
+                        Node::RenderIfElement(RenderIfState::Hidden, new_elem)
+                    } else {
+                        Node::RenderIfElement(RenderIfState::NotRendered, Element::new("", None, 999999))
+                    }
                 },
-                _ => {
-                    panic!("Only cloning element and text is supported so far");
-                }
+                Node::ForEach(rows)=>Node::ForEach(rows.iter()
+                    .map(|(idx, e)| (*idx, e.clone_in(dnode, dchildren, edom)))
+                    .collect()),
+                Node::Fragment(e)=>Node::Fragment(e.clone_in(dnode, dchildren, edom)),
+            };
+            r.children.push(new_child);
+        }
+    }
+
+    /// Clones the element using the next DOM node of `dchildren`, or as many as it has if it's a fragment.
+    fn clone_in(&self, dnode: &EN, dchildren: &mut std::vec::IntoIter<EN::GenericNode>, edom: &mut EDOM<EN>)->Self {
+        if self.is_fragment() {
+            let mut r=self.shallow_clone(Some(dnode.clone()), edom);
+            r.start_timers(edom);
+            self.clone_children_using_dnodes(&mut r, dnode, dchildren, edom);
+            r
+        } else {
+            self.clone_using_dnode(Self::next_dchild(dchildren).into_element_node(), edom)
+        }
+    }
+
+    fn next_dchild(dchildren: &mut std::vec::IntoIter<EN::GenericNode>)->EN::GenericNode {
+        dchildren.next().expect("The cloned DOM has less children than the vdom")
+    }
+
+    pub fn shallow_clone(&self, target_dnode: Option<EN>, edom: &mut EDOM<EN>)->Self {
+        let uid=edom.next_uid();
+        let extras=self.extras.as_ref().map(|x| Box::new(ElementExtras {
+            timer_registrations: x.timer_registrations.iter()
+                .map(|t| TimerRegistration {uid: edom.next_uid(), ..*t}).collect(),
+            content: x.content.clone(), fragment: x.fragment, ..Default::default()}));
+        let r=Self {name: self.name.clone(), attr: self.attr.clone(), children: Vec::new(), dnode: CachedValue::new(target_dnode), events: self.events.clone(), uid,
+            extras};
+        // The cloned DOM node has the tokens of the running transition of the element, but the clone has none.
+        if let (Some(extras), false)=(&self.extras, r.dnode.is_none()) {
+            let dnode=r.dnode.unwrap();
+            for (name, _) in &extras.transition_tokens {
+                dnode.set_attribute(name, r.attribute(name).unwrap_or(""));
             }
-            next_child_idx+=1;
         }
         r
     }
 
-    pub fn shallow_clone(&self, target_dnode: Option<EN>, edom: &mut EDOM<EN>)->Self {
-        Self {name: self.name, attr: self.attr.clone(), children: Vec::new(), dnode: CachedValue::new(target_dnode), events: self.events.clone(), uid: edom.next_uid()}
+    /// The value of `name` in [`Element::attr`].
+    pub fn attribute(&self, name: &str)->Option<&str> {
+        self.attr.iter().find(|a| a.0==name).map(|a| a.1.as_str())
+    }
+
+    /// The value of the attribute `name` in the DOM: `value` with the classes or style declarations of the
+    /// running transition added.
+    pub fn with_transition_tokens<'a>(&self, name: &str, value: &'a str)->std::borrow::Cow<'a, str> {
+        let tokens=self.extras.as_ref().map_or(&[][..], |x| &x.transition_tokens);
+        if !tokens.iter().any(|t| t.0==name) {
+            return value.into();
+        }
+        let (separator, joiner)=if name=="style" { (';', "; ") } else { (' ', " ") };
+        let mut r: Vec<&str>=value.split(separator).map(|t| t.trim()).filter(|t| !t.is_empty()).collect();
+        r.extend(tokens.iter().filter(|t| t.0==name).map(|t| t.1.as_str()));
+        r.join(joiner).into()
+    }
+
+    /// Whether `uid` is the uid of the element or one of its descendants.
+    pub fn contains_uid(&self, uid: u64)->bool {
+        self.uid==uid || self.extras.as_ref().is_some_and(|x| x.timer_registrations.iter().any(|t| t.uid==uid))
+            || self.children.iter().any(|child| match child {
+            Node::Text(..)=>false,
+            Node::Element(e) | Node::RenderIfElement(_, e) | Node::Fragment(e)=>e.contains_uid(uid),
+            Node::ForEach(rows)=>rows.iter().any(|row| row.1.contains_uid(uid)),
+        })
+    }
+
+    /// Stops the timers of the element and its descendants when it's hidden.
+    pub fn stop_timers(&self) {
+        if let Some(extras)=&self.extras {
+            extras.timers.borrow_mut().clear();
+        }
+        for child in &self.children {
+            match child {
+                Node::Text(..)=>(),
+                Node::Element(e) | Node::RenderIfElement(_, e) | Node::Fragment(e)=>e.stop_timers(),
+                Node::ForEach(rows)=>rows.iter().for_each(|row| row.1.stop_timers()),
+            }
+        }
+    }
+
+    /// Starts the timers of the element and its visible descendants again when it's shown, with their
+    /// full interval.
+    pub fn restart_timers(&self, edom: &EDOM<EN>) {
+        self.start_timers(edom);
+        for child in &self.children {
+            match child {
+                Node::Text(..) | Node::RenderIfElement(RenderIfState::Hidden | RenderIfState::NotRendered, _)=>(),
+                Node::Element(e) | Node::RenderIfElement(_, e) | Node::Fragment(e)=>e.restart_timers(edom),
+                Node::ForEach(rows)=>rows.iter().for_each(|row| row.1.restart_timers(edom)),
+            }
+        }
+    }
+
+    /// Removes the DOM children after the ones in the vdom from the element and its descendants
+    /// after hydrating, and adds the mismatches to `mismatches`.
+    pub(crate) fn remove_unhydrated_children(&self, mismatches: &mut Vec<String>) {
+        if !self.is_fragment() && self.content().is_none() {
+            let dnode=self.dnode.unwrap();
+            let len=self.children.iter().map(|child| child.dom_len()).sum::<usize>();
+            while let Some(extra)=dnode.get_child_node(len as u32) {
+                let found=if extra.is_element() {
+                    format!("<{}>", dnode.get_child_node(len as u32).unwrap().into_element_node().tag_name())
+                } else {
+                    "text".to_string()
+                };
+                mismatches.push(format!("expected nothing at position {}, found {}", len, found));
+                dnode.remove_child_node(&extra);
+            }
+        }
+        for child in &self.children {
+            match child {
+                Node::Text(..) | Node::RenderIfElement(RenderIfState::Hidden | RenderIfState::NotRendered, _)=>(),
+                Node::Element(e) | Node::RenderIfElement(_, e) | Node::Fragment(e)=>e.remove_unhydrated_children(mismatches),
+                Node::ForEach(rows)=>rows.iter().for_each(|row| row.1.remove_unhydrated_children(mismatches)),
+            }
+        }
+    }
+
+    /// Drops the [`Visitor::state`] values of the element and its descendants.
+    pub fn clear_state(&mut self) {
+        if let Some(extras)=&mut self.extras {
+            extras.state.clear();
+        }
+        for child in &mut self.children {
+            match child {
+                Node::Text(..)=>(),
+                Node::Element(e) | Node::RenderIfElement(_, e) | Node::Fragment(e)=>e.clear_state(),
+                Node::ForEach(rows)=>rows.iter_mut().for_each(|row| row.1.clear_state()),
+            }
+        }
     }
 
     pub fn partial_clone_using_dnode(&self, target_iterator: Visitor<EN>) {
+        self.partial_clone_children(target_iterator, 0);
+    }
+
+    /// Clones to the element of `target_iterator`, whose children start at `next_dom_node` in its DOM node.
+    /// Returns the DOM position after the children.
+    fn partial_clone_children(&self, mut target_iterator: Visitor<EN>, mut next_dom_node: usize)->usize {
         // Attach events
         for event_name in &self.events {
             target_iterator.element.create_event_listener(event_name, target_iterator.edom, target_iterator.get_dnode());
         }
+        target_iterator.element.start_timers(target_iterator.edom);
         // Attach children
-        let mut next_dom_node=0;
         for child in &self.children {
-            let mut skip=false;
+            let mut skip=false;  // The DOM nodes of the child are counted already or it has none.
             let new_elem=match child {
                 Node::Text(s, _)=>Node::Text(s.clone(), None),
                 Node::Element(e)=> {
@@ -151,7 +417,23 @@ impl<EN> Element<EN>  where EN:dom::ElementNode {
                     Node::Element(new_elem)
                 },
                 Node::RenderIfElement(state, e)=> {
-                    if *state == RenderIfState::Visible {
+                    if *state == RenderIfState::Visible && e.is_fragment() {
+                        skip=true;
+                        let new_elem=e.partial_clone_fragment(&mut target_iterator, &mut next_dom_node);
+                        Node::RenderIfElement(RenderIfState::Visible, new_elem)
+                    } else if *state == RenderIfState::Leaving {
+                        // Hidden like in `clone_children_using_dnodes`.
+                        skip=true;
+                        let parent=target_iterator.get_dnode().clone();
+                        let dnode=parent.get_child_node(next_dom_node as u32)
+                            .expect("The cloned DOM has less children than the vdom").into_element_node();
+                        let mut new_elem=e.shallow_clone(Some(dnode.clone()), target_iterator.edom);
+                        let it=Visitor::new(target_iterator.edom, &mut new_elem, next_dom_node, None);
+                        e.partial_clone_using_dnode(it);
+                        parent.remove_child(&dnode);
+                        new_elem.stop_timers();
+                        Node::RenderIfElement(RenderIfState::Hidden, new_elem)
+                    } else if *state == RenderIfState::Visible {
                         let mut new_elem=e.shallow_clone(None, target_iterator.edom);
                         let ctarget_iterator : *const Visitor<EN>=&target_iterator;
                         let it=Visitor::new(
@@ -163,8 +445,30 @@ impl<EN> Element<EN>  where EN:dom::ElementNode {
                         Node::RenderIfElement(RenderIfState::NotRendered, Element::new("", None, 999999))
                     }
                 }
-                _ => {
-                    panic!("Only cloning element and text is supported so far");
+                Node::ForEach(rows)=> {
+                    // Rows need their DOM nodes for moving them, so they are looked up now.
+                    let mut new_rows=Vec::with_capacity(rows.len());
+                    for (idx, e) in rows {
+                        if e.is_fragment() {
+                            new_rows.push((*idx, e.partial_clone_fragment(&mut target_iterator, &mut next_dom_node)));
+                            continue;
+                        }
+                        let dnode=target_iterator.get_dnode().get_child_node(next_dom_node as u32)
+                            .expect("The cloned DOM has less children than the vdom").into_element_node();
+                        let mut new_elem=e.shallow_clone(Some(dnode), target_iterator.edom);
+                        let ctarget_iterator : *const Visitor<EN>=&target_iterator;
+                        let it=Visitor::new(
+                            target_iterator.edom, &mut new_elem, next_dom_node, Some(ctarget_iterator));
+                        e.partial_clone_using_dnode(it);
+                        new_rows.push((*idx, new_elem));
+                        next_dom_node+=1;
+                    }
+                    skip=true;
+                    Node::ForEach(new_rows)
+                }
+                Node::Fragment(e)=> {
+                    skip=true;
+                    Node::Fragment(e.partial_clone_fragment(&mut target_iterator, &mut next_dom_node))
                 }
             };
             target_iterator.element.children.push(new_elem);
@@ -172,18 +476,76 @@ impl<EN> Element<EN>  where EN:dom::ElementNode {
                 next_dom_node+=1;
             }
         }
+        next_dom_node
+    }
+
+    /// Clones a fragment whose DOM nodes start at `next_dom_node` in the DOM node of `target_iterator`,
+    /// and moves `next_dom_node` after them.
+    fn partial_clone_fragment(&self, target_iterator: &mut Visitor<EN>, next_dom_node: &mut usize)->Self {
+        let dnode=target_iterator.get_dnode().clone();
+        let mut new_elem=self.shallow_clone(Some(dnode), target_iterator.edom);
+        let ctarget_iterator : *const Visitor<EN>=target_iterator;
+        let it=Visitor::new_fragment(target_iterator.edom, &mut new_elem, *next_dom_node, Some(ctarget_iterator));
+        *next_dom_node=self.partial_clone_children(it, *next_dom_node);
+        new_elem
     }
 
+    /// Writes the element as HTML. `value` and `checked` are set as properties in the browser,
+    /// here they are written as the attributes that give the same initial state.
     pub fn render_to(&self, s: &mut String) {
+        if self.is_fragment() {
+            self.render_children_to(s);
+            return;
+        }
         s.push('<');
-        push_quoted_html(s, self.name);
+        s.push_str(&self.name);
+        let mut textarea_value=None;
         for a in &self.attr {
+            if self.name=="textarea" && a.0=="value" {
+                textarea_value=Some(&a.1);
+                continue;
+            }
+            // The visitor rejects these, but the fields of an element can be set directly.
+            if !is_valid_attribute_name(&a.0) {
+                continue;
+            }
+            if is_boolean_attribute(&a.0) {
+                if *a.1 != "false" {
+                    s.push(' ');
+                    s.push_str(&a.0);
+                }
+                continue;
+            }
             s.push(' ');
-            push_quoted_html(s, a.0);
+            s.push_str(&a.0);
             s.push('=');
-            push_quoted_attr_value(s, a.0);
+            push_quoted_attr_value(s, &a.1);
+        }
+        s.push('>');
+        if is_void_element(&self.name) {
+            return;
+        }
+        if let Some(value)=textarea_value {
+            push_quoted_html(s, value);
+        }
+        match self.content() {
+            Some((ContentMode::Text, text))=>for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    s.push_str("<br>");
+                }
+                push_quoted_html(s, line);
+            },
+            Some((ContentMode::Html, html))=>s.push_str(html),
+            None=>(),
         }
+        self.render_children_to(s);
+        s.push_str("</");
+        s.push_str(&self.name);
         s.push('>');
+    }
+
+    /// Writes the children of the element as HTML.
+    pub fn render_children_to(&self, s: &mut String) {
         for child in &self.children {
             match child {
                 Node::Element(e)=>e.render_to(s),
@@ -198,15 +560,38 @@ impl<EN> Element<EN>  where EN:dom::ElementNode {
                         e.1.render_to(s);
                     }
                 }
+                Node::Fragment(e)=>e.render_children_to(s),
             }
         }
-        s.push_str("</");
-        push_quoted_html(s, self.name);
-        s.push('>');
     }
 }
 
-fn push_quoted_html(to: &mut String, s: &str) {
+/// Elements that can't have children and are written without a closing tag.
+pub(crate) fn is_void_element(name: &str)->bool {
+    matches!(name, "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input" | "link" |
+        "meta" | "source" | "track" | "wbr")
+}
+
+/// Whether `name` can be written as an attribute name in HTML: it's not empty and has no whitespace,
+/// control characters, quotes or any of `/`, `=`, `<` and `>`.
+pub fn is_valid_attribute_name(name: &str)->bool {
+    !name.is_empty() && !name.chars().any(|c| c.is_whitespace() || c.is_control() ||
+        matches!(c, '"' | '\'' | '/' | '=' | '<' | '>'))
+}
+
+/// Whether `name` can be written as a tag name in HTML: a valid attribute name that starts with an
+/// ASCII letter, like `div` or `my-widget`.
+pub fn is_valid_tag_name(name: &str)->bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic()) && is_valid_attribute_name(name)
+}
+
+/// Attributes whose presence means true. The visitor sets them to `"true"` or `"false"`.
+pub(crate) fn is_boolean_attribute(name: &str)->bool {
+    matches!(name, "checked" | "selected" | "disabled" | "autofocus" | "readonly" | "required" |
+        "multiple" | "hidden")
+}
+
+pub(crate) fn push_quoted_html(to: &mut String, s: &str) {
     for c in s.chars() {
         match c {
             '<'=> to.push_str("&lt;"),
@@ -217,7 +602,7 @@ fn push_quoted_html(to: &mut String, s: &str) {
     }
 }
 
-fn push_quoted_attr_value(to: &mut String, s: &str) {
+pub(crate) fn push_quoted_attr_value(to: &mut String, s: &str) {
     to.push('"');
     for c in s.chars() {
         match c {
@@ -229,4 +614,113 @@ fn push_quoted_attr_value(to: &mut String, s: &str) {
         }
     }
     to.push('"');
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memdom;
+    use crate::testing::TestApp;
+    use crate::visitor::Visitor;
+
+    struct Tree {
+        id: u32,
+        open: bool,
+        children: Vec<Tree>,
+    }
+
+    fn tree(id: u32, open: bool, children: Vec<Tree>)->Tree {
+        Tree { id, open, children }
+    }
+
+    fn render_tree(tree: &mut Tree, li: &mut Visitor<memdom::ElementNode>, next_id: &mut u32) {
+        li.text(tree.id.to_string().as_str());
+        if li.button("+").clicked() {
+            tree.children.push(self::tree(*next_id, true, Vec::new()));
+            *next_id+=1;
+        }
+        if li.button("toggle").clicked() {
+            tree.open=!tree.open;
+        }
+        li.render_element_if(tree.open, "ul", |ul| {
+            ul.for_each(tree.children.iter_mut(), |t| t.id, "li", |t, li| render_tree(t, li, next_id));
+        });
+    }
+
+    fn tree_app()->TestApp {
+        let mut root_tree=tree(0, true, vec![
+            tree(1, true, vec![tree(2, true, Vec::new())]),
+            tree(3, false, vec![tree(4, true, Vec::new())]),
+        ]);
+        let mut next_id=5;
+        TestApp::new(move |mut root| {
+            root.ul(|ul| ul.for_each(std::iter::once(&mut root_tree), |t| t.id, "li", |t, li| {
+                render_tree(t, li, &mut next_id);
+            }));
+        })
+    }
+
+    #[test]
+    fn nested_rows_are_cloned() {
+        TestApp::in_clone_modes(tree_app, |app| {
+            let text=|| app.body().text_content().replace("+toggle", " ");
+            assert_eq!("0 1 2 3 ", text());
+            // Hide 1 (and 2), so the next row is cloned from a row with a hidden list.
+            app.click(&app.find_all_by_text("toggle")[1]);
+            assert_eq!("0 1 3 ", text());
+            // The new row 5 is cloned from row 1 with its nested rows.
+            app.click(&app.find_all_by_text("+")[0]);
+            assert_eq!("0 1 3 5 ", text());
+            app.click(&app.find_all_by_text("toggle")[1]);
+            assert_eq!("0 1 2 3 5 ", text());
+            // Rows 6 and 7 are cloned from row 2, and row 8 is the first row of the list of 6.
+            app.click(&app.find_all_by_text("+")[1]);
+            app.click(&app.find_all_by_text("+")[1]);
+            app.click(&app.find_all_by_text("+")[3]);
+            assert_eq!("0 1 2 6 8 7 3 5 ", text());
+            app.click(&app.find_all_by_text("toggle")[6]);
+            assert_eq!("0 1 2 6 8 7 3 4 5 ", text());
+            let toggles=app.find_all_by_text("toggle");
+            app.click(&toggles[1]);
+            assert_eq!("0 1 3 4 5 ", text());
+            app.click(&toggles[1]);
+            assert_eq!("0 1 2 6 8 7 3 4 5 ", text());
+        });
+    }
+
+    #[test]
+    fn cloned_rows_with_fragments() {
+        TestApp::in_clone_modes(|| {
+            let mut items=vec![1];
+            TestApp::new(move |mut root| {
+                if root.button("add").clicked() {
+                    items.push(items.iter().max().unwrap_or(&0) + 1);
+                }
+                let mut remove=None;
+                root.ul(|ul| ul.for_each(items.iter(), |i| **i, "li", |i, li| {
+                    li.text("#");
+                    li.fragment(|f| {
+                        f.text(i.to_string().as_str());
+                        f.render_if(*i % 2 == 0, |f| f.text(" even"));
+                    });
+                    if li.button("x").clicked() {
+                        remove=Some(*i);
+                    }
+                }));
+                if let Some(i)=remove {
+                    items.retain(|j| *j != i);
+                }
+            })
+        }, |app| {
+            let text=|| app.body().child_elements()[1].text_content();
+            let add=app.find_by_text("add").unwrap();
+            app.click(&add);
+            app.click(&add);
+            assert_eq!("#1x#2 evenx#3x", text());
+            // The button after the fragment of a cloned row gets the event.
+            app.click(&app.find_all_by_text("x")[1]);
+            assert_eq!("#1x#3x", text());
+            app.click(&add);
+            assert_eq!("#1x#3x#4 evenx", text());
+        });
+    }
+}
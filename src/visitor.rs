@@ -1,14 +1,17 @@
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use wasm_bindgen::convert::IntoWasmAbi;
 
-use crate::dom::{Document, GenericNode, ElementNode};
-use crate::vdom::{RenderIfState, CachedValue};
+use crate::dom::{Document, GenericNode, ElementNode, TextNode};
+use crate::vdom::{RenderIfState, CachedValue, is_boolean_attribute, is_valid_attribute_name, is_valid_tag_name};
+use crate::transition::Transition;
 
 use super::EDOM;
 use super::vdom::{Element,Node};
 use super::dom;
+use super::dom::Name;
 
 pub struct Visitor<'d, 'e, EN> where EN:dom::ElementNode {
     pub edom: &'d mut EDOM<EN>,
@@ -16,26 +19,100 @@ pub struct Visitor<'d, 'e, EN> where EN:dom::ElementNode {
     pub attrpos: usize,
     pub childpos: usize,
     pub eventpos: usize,
+    /// Position of the next [`Visitor::state`] call in the element.
+    pub statepos: usize,
+    /// Position of the next [`Visitor::every`] or [`Visitor::after`] call in the element.
+    pub timerpos: usize,
     pub parent_access_pos: usize,
     pub parent_iterator: Option<*const Visitor<'d, 'd, EN>>,
     pub next_dom_child_pos: usize
 }
 
 impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNode {
-    fn create_element_iterator(&'f mut self, child_pos: usize)->Visitor<'f, 'f, EN> {
+    fn create_element_iterator(&'f mut self, child_pos: usize, dom_pos: usize)->Visitor<'f, 'f, EN> {
         let cself : *const Visitor<EN>=self;
         let Node::Element(element)=&mut self.element.children[child_pos] else {
             panic!("Not Element")
         };
-        Visitor::new(self.edom, element, child_pos, Some(cself))
+        Visitor::new(self.edom, element, dom_pos, Some(cself))
     }
-    fn create_element(&mut self, name : &'static str)->Element<EN> {
-        Element::new(name, Some(self.edom.document.create_element(name)), self.edom.next_uid())
+    fn create_element(&mut self, name : Name)->Element<EN> {
+        let name=valid_tag_name(self.edom, name);
+        Element::new(name.clone(), Some(self.edom.document.create_element(name)), self.edom.next_uid())
     }
-    pub fn element(&'f mut self, name : &'static str)->Visitor<'f, 'f, EN> {
+    /// Binds the DOM child of `parent` at `pos` to a new element while hydrating.
+    fn hydrate_element(edom: &mut EDOM<EN>, parent: &EN, pos: usize, name: Name)->Element<EN> {
+        let name=valid_tag_name(edom, name);
+        let uid=edom.next_uid();
+        let existing=parent.get_child_node(pos as u32);
+        let mut wrong_element=None;
+        match &existing {
+            Some(child) if child.is_element() => {
+                let dnode=parent.get_child_node(pos as u32).unwrap().into_element_node();
+                if dnode.tag_name().eq_ignore_ascii_case(&name) {
+                    return Element::new(name, Some(dnode), uid);
+                }
+                edom.report_hydration_mismatch(format!("expected <{}> at position {}, found <{}>",
+                    name, pos, dnode.tag_name()));
+                wrong_element=Some(dnode);
+            },
+            Some(_) => edom.report_hydration_mismatch(format!("expected <{}> at position {}, found text",
+                name, pos)),
+            None => edom.report_hydration_mismatch(format!("expected <{}> at position {}, found nothing",
+                name, pos)),
+        }
+        let dnode=edom.document.create_element(name.clone());
+        parent.insert_child_before(dnode.into_generic_node(), existing.as_ref());
+        if let Some(wrong_element)=wrong_element {
+            wrong_element.remove();
+        }
+        Element::new(name, Some(dnode), uid)
+    }
+
+    /// Binds the DOM child at the current position to a new text node while hydrating.
+    fn hydrate_text(&mut self, text: &str)->EN::TextNode {
+        let pos=self.next_dom_child_pos;
+        let dnode=Self::get_dnode_using_parameters(&self.element.dnode, &self.parent_iterator, self.parent_access_pos);
+        let existing=dnode.get_child_node(pos as u32);
+        let is_text=matches!(&existing, Some(e) if !e.is_element());
+        if text.is_empty() || !is_text {
+            // Empty text is not present in HTML.
+            if !text.is_empty() {
+                self.edom.report_hydration_mismatch(format!("expected text {:?} at position {}", text, pos));
+            }
+            let tdnode=self.edom.document.create_text_node(text);
+            dnode.insert_text_child_before(&tdnode, existing.as_ref());
+            return tdnode;
+        }
+        let old_tdnode=existing.unwrap().into_text_node();
+        let data=old_tdnode.text_data();
+        if data==text {
+            return old_tdnode;
+        }
+        let tdnode=self.edom.document.create_text_node(text);
+        dnode.replace_text_child(&tdnode, &old_tdnode);
+        if let Some(rest)=data.strip_prefix(text) {
+            // Adjacent text nodes are merged into one when HTML is parsed.
+            let rest=self.edom.document.create_text_node(rest);
+            dnode.insert_text_child_before(&rest, dnode.get_child_node(pos as u32+1).as_ref());
+        } else {
+            self.edom.report_hydration_mismatch(format!("expected text {:?} at position {}, found {:?}",
+                text, pos, data));
+        }
+        tdnode
+    }
+
+    pub fn element(&'f mut self, name : impl Into<Name>)->Visitor<'f, 'f, EN> {
         let new_pos= if self.edom.create {
-            let elem= self.create_element(name);
-            self.get_dnode().append_child(&elem.dnode.get(||panic!("Dnode empty")));
+            let name=name.into();
+            let elem= if self.edom.hydrating {
+                let dnode=Self::get_dnode_using_parameters(&self.element.dnode, &self.parent_iterator, self.parent_access_pos);
+                Self::hydrate_element(self.edom, dnode, self.next_dom_child_pos, name)
+            } else {
+                let elem= self.create_element(name);
+                self.add_created_dnode(elem.dnode.get(||panic!("Dnode empty")));
+                elem
+            };
             let i = self.element.children.len();
             self.element.children.push(Node::Element(elem));
             i
@@ -45,7 +122,7 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
             i
         };
         self.next_dom_child_pos+=1;
-        self.create_element_iterator(new_pos)
+        self.create_element_iterator(new_pos, self.next_dom_child_pos-1)
     }
     fn get_render_if_element_at(&self, child_pos: usize)->&Element<EN> {
         let Node::RenderIfElement(_, e)=&self.element.children[child_pos] else { panic!("Not RenderIfElement")};
@@ -56,17 +133,37 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         e
     }
     fn insert_after_last_visible_dnode(&self, child: &EN::GenericNode) {
-        EN::Document::log_2("Inserting element at", self.next_dom_child_pos.to_string().as_str());
         self.get_dnode().insert_child_before(
             child,
             self.get_dnode().get_child_node(self.next_dom_child_pos as u32).as_ref());
     }
 
-    pub fn render_element_if<FCB>(&'f mut self, should_render: bool, tag: &'static str, mut fcb: FCB) where FCB:FnMut(&mut Visitor<EN>) {
+    /// Adds an element created in a create pass. The children of a new element are appended in order,
+    /// but a fragment can be created between existing DOM nodes of its parent.
+    fn add_created_dnode(&self, child: &EN) {
+        if self.element.is_fragment() {
+            self.insert_after_last_visible_dnode(child.into_generic_node());
+        } else {
+            self.get_dnode().append_child(child);
+        }
+    }
+
+    pub fn render_element_if<FCB>(&'f mut self, should_render: bool, tag: impl Into<Name>, fcb: FCB) where FCB:FnMut(&mut Visitor<EN>) {
+        self.render_element_if_impl(should_render, tag.into(), None, fcb);
+    }
+
+    fn render_if_dnode<'n>(&self, pos: usize)->&'n EN {
+        let child_cached_dnode= &self.get_render_if_element_at(pos).dnode;
+        child_cached_dnode.get(||
+            self.get_dnode().get_child_node(self.next_dom_child_pos as u32).unwrap().into_element_node())
+    }
+
+    pub(crate) fn render_element_if_impl<FCB>(&'f mut self, should_render: bool, tag: Name,
+            transition: Option<&Transition>, mut fcb: FCB) where FCB:FnMut(&mut Visitor<EN>) {
         let cself : *const Visitor<EN>=self;
         let new_pos= if self.edom.create {
             let i = self.element.children.len();
-            self.element.children.push(Node::RenderIfElement(RenderIfState::NotRendered, Element { name: tag, attr: Vec::new(), children: Vec::new(), dnode: CachedValue::new(None), events: Vec::new(), uid: 999999 }));
+            self.element.children.push(Node::RenderIfElement(RenderIfState::NotRendered, Element::new(tag.clone(), None, 999999)));
             i
         } else {
             let i=self.childpos;
@@ -81,51 +178,183 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
                         let child_dnode=child_cached_dnode.get(|| 
                             self.get_dnode().get_child_node(self.next_dom_child_pos as u32).unwrap().into_element_node());
                         self.insert_after_last_visible_dnode(child_dnode.into_generic_node());
+                        if let Some(transition)=transition {
+                            let element=Self::get_render_if_element_from_node(&mut self.element.children[new_pos]);
+                            transition.start_enter(self.edom, element, child_dnode);
+                        }
+                        self.get_render_if_element_at(new_pos).restart_timers(self.edom);
                         self.element.children[new_pos].set_render_if_state(RenderIfState::Visible);
+                        let inserting=std::mem::replace(&mut self.edom.inserting, true);
                         let mut it=Visitor::new(&mut self.edom,
                             Self::get_render_if_element_from_node(&mut self.element.children[new_pos]),
-                            new_pos, Some(cself));
+                            self.next_dom_child_pos, Some(cself));
                         fcb(&mut it);
-    
+                        self.edom.inserting=inserting;
                 }
             },
             RenderIfState::NotRendered => {
                 if should_render {
                     // Need to create new element
-                    let mut elem=self.create_element(tag);
+                    let mut elem=if self.edom.hydrating {
+                        let dnode=Self::get_dnode_using_parameters(&self.element.dnode, &self.parent_iterator, self.parent_access_pos);
+                        Self::hydrate_element(self.edom, dnode, self.next_dom_child_pos, tag)
+                    } else {
+                        let elem=self.create_element(tag);
+                        self.insert_after_last_visible_dnode(elem.dnode.unwrap().into_generic_node());
+                        elem
+                    };
                     let create=self.edom.create;
-                    self.insert_after_last_visible_dnode(elem.dnode.unwrap().into_generic_node());
                     self.edom.create=true;
                     let mut it=Visitor::new(&mut self.edom, 
-                        &mut elem, new_pos, Some(cself));
+                        &mut elem, self.next_dom_child_pos, Some(cself));
                     fcb(&mut it);
                     self.edom.create=create;
+                    // After the callback, so the attributes it sets are not overwritten.
+                    if let (Some(transition), false)=(transition, self.edom.hydrating) {
+                        let dnode=elem.dnode.unwrap();
+                        transition.start_enter(self.edom, &mut elem, dnode);
+                    }
                     self.element.children[new_pos]=Node::RenderIfElement(RenderIfState::Visible, elem);
                 }
             },
             RenderIfState::Visible => {
                 if !should_render {
-                    let child_cached_dnode= &self.get_render_if_element_at(new_pos).dnode;
-                    let child_dnode=child_cached_dnode.get(|| 
-                        self.get_dnode().get_child_node(self.next_dom_child_pos as u32).unwrap().into_element_node());
-                    self.get_dnode().remove_child(child_dnode);
-                    self.element.children[new_pos].set_render_if_state(RenderIfState::Hidden);
+                    let child_dnode=self.render_if_dnode(new_pos);
+                    if let Some(transition)=transition {
+                        let element=Self::get_render_if_element_from_node(&mut self.element.children[new_pos]);
+                        transition.start_leave(self.edom, element, child_dnode);
+                        self.element.children[new_pos].set_render_if_state(RenderIfState::Leaving);
+                    } else {
+                        self.get_dnode().remove_child(child_dnode);
+                        self.element.children[new_pos].set_render_if_state(RenderIfState::Hidden);
+                        let element=Self::get_render_if_element_from_node(&mut self.element.children[new_pos]);
+                        element.clear_state();
+                        element.stop_timers();
+                    }
                 } else {
+                    if let Some(transition)=transition {
+                        let child_dnode=self.render_if_dnode(new_pos);
+                        let element=Self::get_render_if_element_from_node(&mut self.element.children[new_pos]);
+                        transition.on_timer(self.edom, element, child_dnode, false);
+                    }
                     let mut it=Visitor::new(&mut self.edom,
                         Self::get_render_if_element_from_node(&mut self.element.children[new_pos]),
-                        new_pos, Some(cself));
+                        self.next_dom_child_pos, Some(cself));
                     fcb(&mut it);
                 }
+            },
+            RenderIfState::Leaving => {
+                let child_dnode=self.render_if_dnode(new_pos);
+                let element=Self::get_render_if_element_from_node(&mut self.element.children[new_pos]);
+                if should_render {
+                    if let Some(transition)=transition {
+                        transition.start_enter(self.edom, element, child_dnode);
+                    }
+                    self.element.children[new_pos].set_render_if_state(RenderIfState::Visible);
+                    let mut it=Visitor::new(self.edom,
+                        Self::get_render_if_element_from_node(&mut self.element.children[new_pos]),
+                        self.next_dom_child_pos, Some(cself));
+                    fcb(&mut it);
+                } else if transition.is_none_or(|t| t.on_timer(self.edom, element, child_dnode, true)) {
+                    self.get_dnode().remove_child(child_dnode);
+                    self.element.children[new_pos].set_render_if_state(RenderIfState::Hidden);
+                    let element=Self::get_render_if_element_from_node(&mut self.element.children[new_pos]);
+                    element.clear_state();
+                    element.stop_timers();
+                }
             }
         }
-        if should_render {
+        let Node::RenderIfElement(state, _)=&self.element.children[new_pos] else { panic!("Not RenderIfElement")};
+        if matches!(state, RenderIfState::Visible | RenderIfState::Leaving) {
             self.next_dom_child_pos+=1;
         }
     }
-    
+
+    /// Renders the children of `fcb` without a wrapper element if `should_render` is set, like
+    /// [`Visitor::render_element_if`]. The DOM nodes are removed when it's not rendered anymore,
+    /// and created again when it's shown.
+    /// ```
+    /// let mut editing = false;
+    /// let app = edom::testing::TestApp::new(move |mut root| {
+    ///     if root.button("Edit").clicked() {
+    ///         editing = !editing;
+    ///     }
+    ///     root.render_if(editing, |root| {
+    ///         root.text("Title: ");
+    ///         root.element("input");
+    ///     });
+    ///     root.element("hr");
+    /// });
+    /// app.click(&app.find_by_text("Edit").unwrap());
+    /// assert!(app.html().ends_with("Title: <input><hr>"));
+    /// ```
+    pub fn render_if<FCB>(&'f mut self, should_render: bool, mut fcb: FCB) where FCB:FnMut(&mut Visitor<EN>) {
+        let cself : *const Visitor<EN>=self;
+        let new_pos= if self.edom.create {
+            let i = self.element.children.len();
+            self.element.children.push(Node::RenderIfElement(RenderIfState::NotRendered, Element::new("", None, 999999)));
+            i
+        } else {
+            let i=self.childpos;
+            self.childpos+=1;
+            i
+        };
+        let pos=self.next_dom_child_pos;
+        let Node::RenderIfElement(state, elem)=&mut self.element.children[new_pos] else { panic!("Not RenderIfElement")};
+        match (*state==RenderIfState::Visible, should_render) {
+            (false, true) => {
+                let mut elem=Element::fragment(self.get_dnode().clone(), self.edom.next_uid());
+                let create=std::mem::replace(&mut self.edom.create, true);
+                let mut it=Visitor::new_fragment(self.edom, &mut elem, pos, Some(cself));
+                fcb(&mut it);
+                self.edom.create=create;
+                self.element.children[new_pos]=Node::RenderIfElement(RenderIfState::Visible, elem);
+            },
+            (true, true) => {
+                let mut it=Visitor::new_fragment(self.edom, elem, pos, Some(cself));
+                fcb(&mut it);
+            },
+            (true, false) => {
+                let dom_len=elem.dom_len();
+                let dnode=self.get_dnode();
+                for _ in 0..dom_len {
+                    dnode.remove_child_node(&dnode.get_child_node(pos as u32).unwrap());
+                }
+                self.element.children[new_pos]=Node::RenderIfElement(RenderIfState::NotRendered,
+                    Element::new("", None, 999999));
+            },
+            (false, false) => {}
+        }
+        self.next_dom_child_pos+=self.element.children[new_pos].dom_len();
+    }
+
+    /// Renders the children of `fcb` without a wrapper element, in the DOM node of the current element.
+    pub fn fragment<FCB>(&'f mut self, mut fcb: FCB) where FCB:FnMut(&mut Visitor<EN>) {
+        let cself : *const Visitor<EN>=self;
+        let new_pos= if self.edom.create {
+            let elem=Element::fragment(self.get_dnode().clone(), self.edom.next_uid());
+            self.element.children.push(Node::Fragment(elem));
+            self.element.children.len()-1
+        } else {
+            let i=self.childpos;
+            self.childpos+=1;
+            i
+        };
+        let Node::Fragment(elem)=&mut self.element.children[new_pos] else { panic!("Not Fragment")};
+        let mut it=Visitor::new_fragment(self.edom, elem, self.next_dom_child_pos, Some(cself));
+        fcb(&mut it);
+        self.next_dom_child_pos=it.next_dom_child_pos;
+    }
+
     pub fn new(edom:&'d mut EDOM<EN>, element:&'e mut Element<EN>, parent_access_pos: usize, parent_iterator: Option<*const Visitor<'d ,'d,EN>>)->Visitor<'d,'e,EN> {
-         Visitor {edom, element, attrpos: 0, childpos: 0, eventpos: 0, parent_access_pos, parent_iterator, next_dom_child_pos: 0}
+         Visitor {edom, element, attrpos: 0, childpos: 0, eventpos: 0, statepos: 0, timerpos: 0, parent_access_pos, parent_iterator, next_dom_child_pos: 0}
      }
+    /// A visitor of a fragment, whose DOM nodes start at `dom_pos` in the DOM node of the parent.
+    pub fn new_fragment(edom:&'d mut EDOM<EN>, element:&'e mut Element<EN>, dom_pos: usize, parent_iterator: Option<*const Visitor<'d ,'d,EN>>)->Visitor<'d,'e,EN> {
+        let mut it=Visitor::new(edom, element, dom_pos, parent_iterator);
+        it.next_dom_child_pos=dom_pos;
+        it
+    }
     fn get_dnode_using_parameters(dnode: &'a CachedValue<EN>, parent_iterator: &Option<*const Visitor<EN>>, parent_access_pos: usize)->&'a EN {
         dnode.get(|| unsafe {&**parent_iterator.as_ref().unwrap()}.get_dnode().get_child_node(parent_access_pos as u32).unwrap().into_element_node())
     }
@@ -134,9 +363,27 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         // self.element.dnode.get(|| unsafe {(&**self.parent_iterator.as_ref().unwrap())}.get_dnode().get_child_node(self.parent_access_pos as u32).into_element_node())
     }
     
-    pub fn attr(&'f mut self, name: &'static str, value: &str)->&'f mut Visitor<'d,'e,EN> {
-        if self.edom.create {
-            self.get_dnode().set_attribute(name, value);
+    pub fn attr(&'f mut self, name: impl Into<Name>, value: &str)->&'f mut Visitor<'d,'e,EN> {
+        debug_assert!(!self.element.is_fragment(), "A fragment has no attributes");
+        let name=name.into();
+        // Attributes with invalid names are kept in the vdom, but not set in the DOM.
+        if self.edom.create && !is_valid_name(&name, is_valid_attribute_name) {
+            self.edom.report_invalid_name(format!("invalid attribute name {:?} skipped", name));
+            self.element.attr.push((name, Rc::new(value.into())));
+            return self;
+        }
+        if self.edom.hydrating {
+            // Boolean attributes other than `checked` can't be read back as "true" or "false".
+            if !is_boolean_attribute(&name) || name=="checked" {
+                let current=self.get_dnode().get_attribute(&name);
+                if current != value {
+                    self.edom.report_hydration_mismatch(format!("expected {}={:?}, found {:?}", name, value, current));
+                    self.get_dnode().set_attribute(&name, value);
+                }
+            }
+            self.element.attr.push((name, Rc::new(value.into())));
+        } else if self.edom.create {
+            self.get_dnode().set_attribute(&name, value);
             self.element.attr.push((name, Rc::new(value.into())));
         } else { 
             let thisattr=&mut self.element.attr[self.attrpos];
@@ -146,7 +393,11 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
             if *thisattr.1 != value {
                 thisattr.1=Rc::new(value.into());
                 // thisattr.1=value.into();
-                self.get_dnode().set_attribute(name, value);
+                if is_valid_name(&name, is_valid_attribute_name) {
+                    // The classes and styles of a running transition are kept.
+                    let value=self.element.with_transition_tokens(&name, value);
+                    self.get_dnode().set_attribute(&name, &value);
+                }
             }
             self.attrpos+=1
         }
@@ -154,13 +405,21 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
     }
     
     pub fn text(&mut self, text:&str) {
-        if self.edom.create {
-            if self.element.children.len()==0 {
+        if self.edom.hydrating {
+            let tdnode=self.hydrate_text(text);
+            self.element.children.push(Node::Text(Rc::new(text.into()), Some(tdnode)));
+        } else if self.edom.create {
+            if self.element.children.len()==0 && !self.element.is_fragment() {
                 self.get_dnode().set_text_content(text);
                 self.element.children.push(Node::Text(Rc::new(text.into()), None));
             } else {
                 let tdnode=self.edom.document.create_text_node(text);
-                self.get_dnode().append_text_child(&tdnode);
+                if self.element.is_fragment() {
+                    let dnode=self.get_dnode();
+                    dnode.insert_text_child_before(&tdnode, dnode.get_child_node(self.next_dom_child_pos as u32).as_ref());
+                } else {
+                    self.get_dnode().append_text_child(&tdnode);
+                }
                 let elem=Node::Text(Rc::new(text.into()), Some(tdnode));
                 self.element.children.push(elem);
             }
@@ -182,29 +441,32 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
 
     fn update_text_content_for_current_child(&mut self) {
         let n=self.element.children.len();
+        let fragment=self.element.is_fragment();
         let dnode=self.element.dnode.unwrap();
         let elem = &mut self.element.children[self.childpos];
         let Node::Text(text, text_dnode)=elem else {
             panic!("No text child found");
         };
 
-        if n==1 {
+        if n==1 && !fragment {
             *text_dnode=None;
             dnode.set_text_content(text);
         } else {
             let new_child=self.edom.document.create_text_node(text);
             if text_dnode.is_none() {
-                *text_dnode=Some(dnode.get_child_node(self.childpos as u32).unwrap().into_text_node());
+                *text_dnode=Some(dnode.get_child_node(self.next_dom_child_pos as u32).unwrap().into_text_node());
             }
             dnode.replace_text_child(&new_child, text_dnode.as_ref().unwrap());
             *text_dnode=Some(new_child);
         }
     }
 
-    pub fn on<F>(&'f mut self, name:&'static str, mut f: F)->&'f mut Self where F:FnMut(&EN::Event) {
+    pub fn on<F>(&'f mut self, name:impl Into<Name>, mut f: F)->&'f mut Self where F:FnMut(&EN::Event) {
+        debug_assert!(!self.element.is_fragment(), "A fragment has no events");
         if self.edom.create {
+            let name=name.into();
+            self.element.create_event_listener(&name, self.edom, self.get_dnode());
             self.element.events.push(name);
-            self.element.create_event_listener(name, self.edom, self.get_dnode());
         } else if let Some(ev) = &self.edom.firing_event  {
             if self.element.uid == ev.0 {
                 if *self.element.events[self.eventpos]==*ev.1  {
@@ -226,7 +488,7 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
 
     fn for_each_consolidate_changes<'g, TIdx: std::hash::Hash, FIdx, FCB, I,
             L: Iterator<Item=I>>(
-            &'f mut self, list : L, mut fidx: FIdx, tag: &'static str, mut fcb: FCB) 
+            &'f mut self, list : L, mut fidx: FIdx, tag: Option<Name>, mut fcb: FCB) 
             where FIdx:FnMut(&I)->TIdx, FCB:FnMut(I, &mut Visitor<EN>),
             TIdx: std::cmp::Eq {
         let self_ptr : *mut Visitor<EN>=self;
@@ -242,6 +504,21 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         let mut wrong_place: HashSet<u64>=HashSet::new();
         let mut edom : &mut EDOM<EN>=&mut self.edom;
         let mut i=0;
+        // Moving the focused element (or its ancestor) in the DOM blurs it, so it's focused again.
+        let mut focused: Option<Option<EN>>=None;
+        // DOM position of the first row, after the siblings rendered before the list.
+        let start=self.next_dom_child_pos;
+        // The DOM nodes of fragment rows, looked up before anything is moved.
+        let mut fragment_dnodes: HashMap<u64, Vec<EN::GenericNode>>=HashMap::new();
+        if tag.is_none() {
+            let mut pos=start;
+            for (idx, row) in v.iter() {
+                let len=row.dom_len();
+                fragment_dnodes.insert(*idx,
+                    (pos..pos+len).map(|p| dnode.get_child_node(p as u32).unwrap()).collect());
+                pos+=len;
+            }
+        }
 
         for e in list {
             let mut hasher= std::collections::hash_map::DefaultHasher::new();
@@ -271,16 +548,27 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
                 if wrong_place.contains(&idx) {
                     // Needed this syntax because of a bug in rust-analyzer.
                     HashSet::<u64>::remove(&mut wrong_place, &idx);
-                    if i==0 {
-                        // TODO: get last visited child to support for_each after other elements
-                        dnode.prepend_child(&v[i].1.dnode.unwrap());
-                    } else {
-                        dnode.append_child_after(&v[i].1.dnode.unwrap(),
-                        &v[i-1].1.dnode.unwrap())
+                    if tag.is_some() {
+                        focused.get_or_insert_with(|| edom.document.active_element());
+                        let prev=if i==0 {None} else {Some(v[i-1].1.dnode.unwrap())};
+                        Self::place_for_each_row(dnode, v[i].1.dnode.unwrap(), start, prev);
+                    }
+                }
+                // Only element rows keep their order with the swaps, so each fragment row is checked.
+                if let Some(row_dnodes)=fragment_dnodes.get(&idx) {
+                    let pos=self.next_dom_child_pos;
+                    if !row_dnodes.is_empty() && dnode.get_child_node(pos as u32).as_ref()!=row_dnodes.first() {
+                        focused.get_or_insert_with(|| edom.document.active_element());
+                        for (k, row_dnode) in row_dnodes.iter().enumerate() {
+                            dnode.insert_child_before(row_dnode, dnode.get_child_node((pos+k) as u32).as_ref());
+                        }
                     }
                 }
-                let mut it : Visitor<EN>=Visitor::new(
-                    edom, &mut v[i].1, self.next_dom_child_pos, Some(self_ptr));
+                let mut it : Visitor<EN>=if tag.is_none() {
+                    Visitor::new_fragment(edom, &mut v[i].1, self.next_dom_child_pos, Some(self_ptr))
+                } else {
+                    Visitor::new(edom, &mut v[i].1, self.next_dom_child_pos, Some(self_ptr))
+                };
                 fcb(e, &mut it);
                 edom=it.edom;
             } else {
@@ -288,72 +576,103 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
                 let elem=Self::create_for_each_element(
                     e, edom, self.next_dom_child_pos,
                     &mut fcb, self_ptr, 
-                    tag, last_elem);
+                    tag.as_ref(), last_elem);
 
                 v.insert(i, (idx, elem));
                 relpos+=1;
                     
-                if i+1 == v.len() {
-                    dnode.append_child(&v[i].1.dnode.unwrap());
-                } else {
-                    dnode.insert_child_before(&v[i].1.dnode.unwrap().into_generic_node(), 
-                        Some(&v[i+1].1.dnode.unwrap().into_generic_node()));  // Not sure that this is the next sibling
+                // The DOM nodes of fragment rows are created in place.
+                if tag.is_some() {
+                    let prev=if i==0 {None} else {Some(v[i-1].1.dnode.unwrap())};
+                    Self::place_for_each_row(dnode, v[i].1.dnode.unwrap(), start, prev);
                 }
             }
+            self.next_dom_child_pos+=v[i].1.dom_len();
             i+=1;
-            self.next_dom_child_pos+=1;
         }
 
         while v.len() > i {  // Remove remaining children
-            v.last().unwrap().1.dnode.unwrap().remove();
+            let (idx, row)=v.last().unwrap();
+            match fragment_dnodes.get(idx) {
+                Some(row_dnodes)=>row_dnodes.iter().for_each(|row_dnode| dnode.remove_child_node(row_dnode)),
+                None=>row.dnode.unwrap().remove(),
+            }
             v.pop();
         }
+        if let Some(Some(focused))=focused {
+            if !focused.has_focus() && edom.focus_request.is_none() {
+                edom.focus_request=Some(focused);
+            }
+        }
 
         self.childpos+=1;
     }
 
+    /// Moves or inserts a row right after the previous row of the list. The first row goes before the
+    /// node at the DOM position of the list, so siblings before and after the list stay in place.
+    fn place_for_each_row(dnode: &EN, row: &EN, start: usize, prev: Option<&EN>) {
+        match prev {
+            Some(prev) => dnode.append_child_after(row, prev),
+            None => dnode.insert_child_before(row.into_generic_node(), dnode.get_child_node(start as u32).as_ref()),
+        }
+    }
+
     fn create_for_each_element<'x, FCB, I>(
             item: I, mut edom: &'x mut EDOM<EN>, parent_access_pos: usize,
             mut fcb: FCB, self_ptr: *const Visitor<'x, 'x, EN>, 
-            tag: &'static str, last_elem: Option<&Element<EN>>)->Element<EN> 
+            tag: Option<&Name>, last_elem: Option<&Element<EN>>)->Element<EN> 
             where FCB:FnMut(I, &mut Visitor<EN>)  {
         // Create new DOM or clone.
         let mut element:Element<EN>;
-        if last_elem.is_none() || !edom.clone_for_each {
+        let Some(tag)=tag else {
+            // A fragment row has no DOM node to clone, its children are created in place.
             let create=edom.create;
             edom.create=true;
-            element=Element::new(tag, Some(edom.document.create_element(tag)), edom.next_uid());
-            let mut it:Visitor<EN>=Visitor::new(edom, &mut element, parent_access_pos, Some(self_ptr));
+            let dnode=unsafe {&*self_ptr}.get_dnode().clone();
+            element=Element::fragment(dnode, edom.next_uid());
+            let mut it:Visitor<EN>=Visitor::new_fragment(edom, &mut element, parent_access_pos, Some(self_ptr));
             fcb(item, &mut it);
-            edom=it.edom;
-            edom.create=create;
-        } else {
-            let new_dnode=last_elem.as_ref().unwrap().dnode.unwrap().deep_clone();
+            it.edom.create=create;
+            return element;
+        };
+        let Some(last_elem)=last_elem.filter(|_| edom.clone_for_each) else {
+            let tag=valid_tag_name(edom, tag.clone());
             let create=edom.create;
-            edom.create=false;
-            if edom.use_partial_clone {
-                element=last_elem.unwrap().shallow_clone(Some(new_dnode), edom);
-                let it=Visitor::new(edom, &mut element, parent_access_pos, None);
-                last_elem.unwrap().partial_clone_using_dnode(it);
-            } else {
-                element=last_elem.unwrap().clone_using_dnode(new_dnode, edom);
-            }
+            edom.create=true;
+            element=Element::new(tag.clone(), Some(edom.document.create_element(tag)), edom.next_uid());
             let mut it:Visitor<EN>=Visitor::new(edom, &mut element, parent_access_pos, Some(self_ptr));
             fcb(item, &mut it);
-            edom=it.edom;
-            edom.create=create;
+            it.edom.create=create;
+            return element;
         };
+        let new_dnode=last_elem.dnode.unwrap().deep_clone();
+        let create=edom.create;
+        edom.create=false;
+        let inserting=std::mem::replace(&mut edom.inserting, true);
+        if edom.use_partial_clone {
+            element=last_elem.shallow_clone(Some(new_dnode), edom);
+            let it=Visitor::new(edom, &mut element, parent_access_pos, None);
+            last_elem.partial_clone_using_dnode(it);
+        } else {
+            element=last_elem.clone_using_dnode(new_dnode, edom);
+        }
+        let mut it:Visitor<EN>=Visitor::new(edom, &mut element, parent_access_pos, Some(self_ptr));
+        fcb(item, &mut it);
+        edom=it.edom;
+        edom.create=create;
+        edom.inserting=inserting;
         element
     }
 
     pub fn create_for_each<FIdx, FCB, I, L: Iterator<Item=I>, TIdx : Hash>(&mut self, list : L, 
-            mut fidx: FIdx, tag: &'static str, mut fcb: FCB)
+            mut fidx: FIdx, tag: Option<Name>, mut fcb: FCB)
             where FIdx:FnMut(&I)->TIdx, FCB:FnMut(I, &mut Visitor<EN>) {
         let self_ptr : *mut Visitor<EN>=self;
-       
+
         self.get_dnode();
         self.element.children.push(Node::ForEach(Vec::new()));
         let element=&mut self.element;
+        let in_fragment=element.is_fragment();
         let Node::ForEach(v)=element.children.last_mut().unwrap() else {
             panic!("Not foreach")
         };
@@ -369,23 +688,52 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
                 panic!("Idx must be unique.")
             }
 
-            let elem=Self::create_for_each_element(
-                l, self.edom, v.len(),
-                &mut fcb, self_ptr, 
-                tag, last_elem);
-
-            let elem_dnode=elem.dnode.unwrap();
-            dnode.append_child(elem_dnode);
+            let elem=if let (true, Some(tag))=(self.edom.hydrating, &tag) {
+                let mut elem=Self::hydrate_element(self.edom, dnode, self.next_dom_child_pos, tag.clone());
+                let mut it:Visitor<EN>=Visitor::new(self.edom, &mut elem, self.next_dom_child_pos, Some(self_ptr));
+                fcb(l, &mut it);
+                elem
+            } else {
+                let elem=Self::create_for_each_element(
+                    l, self.edom, self.next_dom_child_pos,
+                    &mut fcb, self_ptr, 
+                    tag.as_ref(), last_elem);
+                if elem.is_fragment() {
+                    // Its DOM nodes are added already.
+                } else if in_fragment {
+                    dnode.insert_child_before(elem.dnode.unwrap().into_generic_node(),
+                        dnode.get_child_node(self.next_dom_child_pos as u32).as_ref());
+                } else {
+                    dnode.append_child(elem.dnode.unwrap());
+                }
+                elem
+            };
+            self.next_dom_child_pos+=elem.dom_len();
             v.push((idx, elem));
             last_elem=Some(&v.last().unwrap().1);
-            self.next_dom_child_pos+=1;
         }
     }
 
+    /// Renders a `tag` element for each item of `list` with `fcb`, see [`Node::ForEach`].
     pub fn for_each<FIdx, TIdx : std::cmp::Eq, FCB, I, L: Iterator<Item=I>>(&'f mut self, list : L,
-            fidx: FIdx, tag: &'static str, fcb: FCB)
+            fidx: FIdx, tag: impl Into<Name>, fcb: FCB)
             where FIdx:FnMut(&I)->TIdx, FCB:FnMut(I, &mut Visitor<EN>),
             TIdx : Hash {
+        self.for_each_impl(list, fidx, Some(tag.into()), fcb);
+    }
+
+    /// Like [`Visitor::for_each`], but each row is a fragment, so it can have any number of DOM nodes.
+    pub fn for_each_fragment<FIdx, TIdx, FCB, I, L>(&'f mut self, list : L,
+            fidx: FIdx, fcb: FCB)
+            where FIdx:FnMut(&I)->TIdx, FCB:FnMut(I, &mut Visitor<EN>),
+            TIdx : Eq + Hash, L: Iterator<Item=I> {
+        self.for_each_impl(list, fidx, None, fcb);
+    }
+
+    fn for_each_impl<FIdx, TIdx, FCB, I, L>(&'f mut self, list : L,
+            fidx: FIdx, tag: Option<Name>, fcb: FCB)
+            where FIdx:FnMut(&I)->TIdx, FCB:FnMut(I, &mut Visitor<EN>),
+            TIdx : Eq + Hash, L: Iterator<Item=I> {
         if self.edom.create {
             self.create_for_each(list, fidx, tag, fcb);
         } else {
@@ -394,6 +742,27 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
     }
 }
 
+/// Whether `name` is valid. Only names that aren't `&'static str` literals are checked in release
+/// builds, because they can come from data.
+fn is_valid_name(name: &Name, is_valid: fn(&str)->bool)->bool {
+    match name {
+        Cow::Borrowed(name)=>{
+            debug_assert!(is_valid(name), "Invalid name: {:?}", name);
+            true
+        },
+        Cow::Owned(name)=>is_valid(name),
+    }
+}
+
+/// Replaces a tag name that isn't valid, for example because it came from data, with `div`.
+fn valid_tag_name<EN>(edom: &mut EDOM<EN>, name: Name)->Name where EN:dom::ElementNode {
+    if is_valid_name(&name, is_valid_tag_name) {
+        return name;
+    }
+    edom.report_invalid_name(format!("invalid tag name {:?} rendered as div", name));
+    "div".into()
+}
+
 fn add_offset(big_indexer: usize, delta: isize) -> Option<usize> {
     if delta < 0 {
         big_indexer.checked_sub(delta.wrapping_abs() as usize)
@@ -402,3 +771,130 @@ fn add_offset(big_indexer: usize, delta: isize) -> Option<usize> {
     }
 }
 use std::hash::{Hash, Hasher};
+
+#[cfg(test)]
+mod tests {
+    use crate::memdom;
+    use crate::testing::TestApp;
+
+    #[test]
+    fn lists_between_siblings() {
+        let mut a=vec![1, 2, 3];
+        let mut b=vec![7, 8];
+        let app=TestApp::new(move |mut root| {
+            if root.button("reverse").clicked() {
+                a.reverse();
+            }
+            if root.button("shift").clicked() {
+                let first=a.remove(0);
+                a.push(first);
+                b.insert(0, first + 10);
+            }
+            if root.button("clear").clicked() {
+                b=if b.is_empty() { vec![9] } else { Vec::new() };
+                a.retain(|i| i % 2 == 1);
+            }
+            root.div(|div| {
+                div.text("<");
+                div.for_each(a.iter(), |i| **i, "span", |i, span| span.text(i.to_string().as_str()));
+                div.header(|h| h.text("|"));
+                div.for_each(b.iter(), |i| **i, "i", |i, e| e.text(i.to_string().as_str()));
+                div.text(">");
+            });
+        });
+        let text=|| app.body().child_elements()[3].text_content();
+        assert_eq!("<123|78>", text());
+        app.click(&app.find_by_text("reverse").unwrap());
+        assert_eq!("<321|78>", text());
+        app.click(&app.find_by_text("shift").unwrap());
+        assert_eq!("<213|1378>", text());
+        app.click(&app.find_by_text("shift").unwrap());
+        assert_eq!("<132|121378>", text());
+        app.click(&app.find_by_text("reverse").unwrap());
+        assert_eq!("<231|121378>", text());
+        app.click(&app.find_by_text("clear").unwrap());
+        assert_eq!("<31|>", text());
+        app.click(&app.find_by_text("clear").unwrap());
+        assert_eq!("<31|9>", text());
+    }
+
+    #[test]
+    fn fragments() {
+        let mut items=vec![1, 2, 3];
+        let mut details=false;
+        let app=TestApp::new(move |mut root| {
+            if root.button("details").clicked() {
+                details=!details;
+            }
+            if root.button("reverse").clicked() {
+                items.reverse();
+            }
+            if root.button("add").clicked() {
+                items.insert(1, items.len() as i32 + 1);
+            }
+            if root.button("remove").clicked() {
+                items.remove(0);
+            }
+            root.div(|div| {
+                div.text("<");
+                div.for_each_fragment(items.iter(), |i| **i, |i, row| {
+                    row.element("b").text(i.to_string().as_str());
+                    row.render_if(details && *i % 2 == 1, |row| row.text("*"));
+                    row.text(";");
+                });
+                div.fragment(|f| {
+                    f.render_if(details, |f| f.text("details"));
+                    f.text(">");
+                });
+            });
+        });
+        let text=|| app.body().child_elements()[4].text_content();
+        let click=|text: &str| app.click(&app.find_by_text(text).unwrap());
+        assert_eq!("<1;2;3;>", text());
+        click("details");
+        assert_eq!("<1*;2;3*;details>", text());
+        let rows=app.body().child_elements()[4].child_elements();
+        click("reverse");
+        assert_eq!("<3*;2;1*;details>", text());
+        // The DOM nodes of the rows are moved.
+        let mut reversed=app.body().child_elements()[4].child_elements();
+        reversed.reverse();
+        assert!(rows == reversed);
+        click("add");
+        assert_eq!("<3*;4;2;1*;details>", text());
+        click("details");
+        assert_eq!("<3;4;2;1;>", text());
+        click("remove");
+        assert_eq!("<4;2;1;>", text());
+        click("reverse");
+        assert_eq!("<1;2;4;>", text());
+        click("details");
+        assert_eq!("<1*;2;4;details>", text());
+        assert_eq!(vec!["1", "2", "4"], app.find_all_by_tag("b").iter().map(|b| b.text.clone()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn names_from_data() {
+        let fields=vec![("name".to_string(), "sl-input"), ("color".to_string(), "sl-color-picker")];
+        let option_tag=String::from("sl-option");
+        let mut changed=Vec::new();
+        let app=TestApp::new(move |mut root| {
+            for (name, tag) in &fields {
+                let mut input=root.element(tag.to_string());
+                input.attr(format!("data-{}", name), "1");
+                input.on(format!("{}-change", tag), |_| changed.push(name.clone()));
+            }
+            root.for_each(changed.iter(), |c| (*c).clone(), option_tag.clone(), |c, option| {
+                option.attr(String::from("data-field"), c);
+            });
+        });
+        assert_eq!("<sl-input data-name=\"1\" data-uid=\"1\"></sl-input>\
+            <sl-color-picker data-color=\"1\" data-uid=\"2\"></sl-color-picker>", app.html());
+        let picker=app.find_by_tag("sl-color-picker").unwrap();
+        assert_eq!(Some("1"), picker.attribute("data-color"));
+        app.fire(&picker, "sl-color-picker-change", memdom::Event::new());
+        app.fire(&app.find_by_tag("sl-input").unwrap(), "sl-input-change", memdom::Event::new());
+        assert!(app.html().ends_with("<sl-option data-field=\"color\"></sl-option>\
+            <sl-option data-field=\"name\"></sl-option>"));
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
 use crate::dom::Event;
 use super::dom;
 use super::dom::Document;
 use super::visitor::Visitor;
+use super::vdom::{ContentMode, Node};
+use super::dom::GenericNode;
 
 impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNode {
     pub fn button(&'f mut self, text: &str)->Visitor<'f,'f,EN> {
@@ -18,7 +22,7 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         return r;
     }
     pub fn element_with_children<FCB:FnMut(&mut Visitor<EN>)>(
-            &'f mut self, name: &'static str, mut fcb: FCB)->Visitor<'f,'f,EN>  {
+            &'f mut self, name: impl Into<dom::Name>, mut fcb: FCB)->Visitor<'f,'f,EN>  {
         let mut r=self.element(name);
         fcb(&mut r);
         return r;
@@ -58,9 +62,48 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         return r;
     }
 
+    /// Focuses the element immediately. The element may not be inserted into the DOM yet in the
+    /// pass that creates it; use [`Visitor::request_focus`] for that.
     pub fn focus(&self) {
         self.get_dnode().focus();
     }
+    /// Focuses the element after the pass that updates the DOM, when the element is already
+    /// inserted. Call it once, in the pass where the focus should move.
+    /// The last request of a pass wins.
+    pub fn request_focus(&mut self)->&mut Self {
+        self.edom.focus_request=Some(self.get_dnode().clone());
+        self
+    }
+    /// Whether the element has the focus, tracked with its `focus` and `blur` events:
+    /// ```
+    /// let mut query = String::new();
+    /// let app = edom::testing::TestApp::new(move |mut root| {
+    ///     let mut input = root.text_input(&mut query);
+    ///     let focused = input.has_focus();
+    ///     input.autofocus(true);
+    ///     root.render_element_if(!focused, "p", |p| { p.text("Press / to search"); });
+    /// });
+    /// app.run_until_stalled();
+    /// assert_eq!(None, app.find_by_tag("p"));
+    /// ```
+    pub fn has_focus(&mut self)->bool {
+        let mut focused=None;
+        self.on("focus", |_| focused=Some(true));
+        self.on("blur", |_| focused=Some(false));
+        let uid=self.element.uid;
+        match focused {
+            Some(true)=>self.edom.focused=Some(uid),
+            Some(false) if self.edom.focused==Some(uid)=>self.edom.focused=None,
+            _=>(),
+        }
+        self.edom.focused==Some(uid)
+    }
+    /// Returns true in the render pass that is caused by the element losing the focus.
+    pub fn lost_focus(&mut self)->bool {
+        let mut r=false;
+        self.on("blur", |_| r=true);
+        r
+    }
 
     pub fn placeholder(&'f mut self, text: &str)->&'f mut Self {
         self.attr("placeholder", text)
@@ -68,7 +111,12 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
     pub fn style(&'f mut self, text: &str)->&'f mut Self {
         self.attr("style", text)
     }
+    /// Sets the `autofocus` attribute, and if `value` is true, requests the focus whenever the element
+    /// is inserted into the DOM (not only when the page is loaded, like the attribute does).
     pub fn autofocus(&'f mut self, value: bool)->&'f mut Self {
+        if value && (self.edom.create || self.edom.inserting) {
+            self.request_focus();
+        }
         self.attr("autofocus", value.to_string().as_str())
     }
     pub fn h1(&'f mut self)->Visitor<'f,'f,EN> {
@@ -97,7 +145,7 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         r.attr("value", value.to_string().as_str());
         if r.changed() {
             *value=r.get_dnode().get_attribute("value").parse::<f64>().unwrap();
-            r.element.attr[0]=("value", Rc::new(value.to_string()));
+            r.element.attr[0].1=Rc::new(value.to_string());
         }
         r
     }
@@ -119,7 +167,7 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         cb.attr("checked", checked.to_string().as_str());
         if cb.changed() {
             *checked=!*checked;
-            cb.element.attr[0]=("checked", Rc::new(checked.to_string()));
+            cb.element.attr[0].1=Rc::new(checked.to_string());
         }
         cb.attr("type", "checkbox");
         cb
@@ -130,7 +178,7 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         cb.attr("checked", checked.to_string().as_str());
         if cb.changed() {
             *checked=!*checked;
-            cb.element.attr[0]=("checked", Rc::new(checked.to_string()));
+            cb.element.attr[0].1=Rc::new(checked.to_string());
         }
         cb.attr("type", "radio").attr("name", name).attr("value", value);
         cb
@@ -144,7 +192,7 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         if changed {
             *value=r.get_dnode().get_attribute("value");
             EN::Document::log_2("changed to", (*value).as_str());
-            r.element.attr[0]=("value", Rc::new(value.to_string()));
+            r.element.attr[0].1=Rc::new(value.to_string());
         }
         r.attr("type", "text");
         r
@@ -154,10 +202,107 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
         r.attr("value", value.as_str());
         if r.changed() {
             *value=r.get_dnode().get_attribute("value");
-            r.element.attr[0]=("value", Rc::new(value.to_string()));
+            r.element.attr[0].1=Rc::new(value.to_string());
         }
         r
     }
+    /// A `div` with `contenteditable` whose text or inner HTML (depending on `mode`) is bound to
+    /// `value`. Edits of the user are read on `input` events; the DOM is only written when `value`
+    /// was changed by the application, so the caret stays where it is while typing.
+    /// The children of the element are not managed by the vdom, so no children should be added.
+    pub fn content_editable(&'f mut self, value: &mut String, mode: ContentMode)->Visitor<'f,'f,EN> {
+        let mut r=self.element("div");
+        r.attr("contenteditable", "true");
+        let mut changed=false;
+        r.on("input", |_| changed=true);
+        let dnode=r.get_dnode();
+        if changed {
+            *value=match mode {
+                ContentMode::Text=>dnode.inner_text(),
+                ContentMode::Html=>dnode.inner_html(),
+            };
+        }
+        let written=r.element.content().is_some_and(|(m, content)| *m==mode && **content==*value);
+        if !written {
+            // After an input event the DOM already has the new content.
+            if !changed {
+                match mode {
+                    ContentMode::Text=>dnode.set_inner_text(value.as_str()),
+                    ContentMode::Html=>dnode.set_inner_html(value.as_str()),
+                }
+            }
+            r.element.extras_mut().content=Some((mode, Rc::new(value.clone())));
+        }
+        r
+    }
+    /// A `select` element bound to `value`, with an `option` for each of `options` (value and label).
+    /// Options are keyed by the hash of their values like in [`Visitor::for_each`], and the one equal
+    /// to `value` is selected. When the user chooses another option, `value` is set to its value.
+    /// Options with the same value as an earlier option are skipped, as their keys would be equal.
+    pub fn select<T, L, I>(&'f mut self, value: &mut T, options: I)->Visitor<'f,'f,EN>
+            where T: Hash + Eq + Clone, L: AsRef<str>, I: IntoIterator<Item=(T, L)> {
+        let mut r=self.element("select");
+        let mut changed=false;
+        r.on("change", |_| changed=true);
+        let options=unique_options(options);
+        if changed {
+            let selected=r.selected_option_keys();
+            if let Some(option)=options.iter().find(|o| selected.first()==Some(&hash_key(&o.0))) {
+                *value=option.0.clone();
+            }
+        }
+        let value=&*value;
+        r.for_each(options.into_iter(), |o| o.0.clone(), "option", |o, option| {
+            // selected attribute is treated specially in dnode.set_attribute
+            let selected=(o.0==*value).to_string();
+            option.attr("selected", selected.as_str());
+            if changed {
+                // The user changed the selection in the DOM, even if the attribute is unchanged.
+                option.get_dnode().set_attribute("selected", selected.as_str());
+            }
+            option.text(o.1.as_ref());
+        });
+        r
+    }
+
+    /// A `select` element with the `multiple` attribute, whose options are selected if their values
+    /// are in `selection` (a `HashSet` or a `Vec`). When the user changes the selection, the values
+    /// of the options are added to or removed from `selection`; other values in it are kept.
+    /// Options with the same value as an earlier option are skipped, like in [`Visitor::select`].
+    pub fn multi_select<T, S, L, I>(&'f mut self, selection: &mut S, options: I)->Visitor<'f,'f,EN>
+            where T: Hash + Eq + Clone, S: MultiSelection<T>, L: AsRef<str>, I: IntoIterator<Item=(T, L)> {
+        let mut r=self.element("select");
+        r.attr("multiple", "true");
+        let mut changed=false;
+        r.on("change", |_| changed=true);
+        let options=unique_options(options);
+        if changed {
+            let selected=r.selected_option_keys();
+            for option in &options {
+                selection.set_selected(&option.0, selected.contains(&hash_key(&option.0)));
+            }
+        }
+        let selection=&*selection;
+        r.for_each(options.into_iter(), |o| o.0.clone(), "option", |o, option| {
+            let selected=selection.is_selected(&o.0).to_string();
+            option.attr("selected", selected.as_str());
+            if changed {
+                option.get_dnode().set_attribute("selected", selected.as_str());
+            }
+            option.text(o.1.as_ref());
+        });
+        r
+    }
+
+    /// Keys of the options that are selected in the DOM, in the order of the last render.
+    fn selected_option_keys(&self)->Vec<u64> {
+        let Some(Node::ForEach(rows))=self.element.children.first() else { return Vec::new() };
+        let dnodes=self.get_dnode().get_child_nodes().into_iter()
+            .filter(|n| n.is_element()).map(|n| n.into_element_node());
+        rows.iter().zip(dnodes).filter(|(_, dnode)| dnode.get_attribute("selected")=="true")
+            .map(|(row, _)| row.0).collect()
+    }
+
     pub fn changed(&mut self)->bool {
         let mut changed=false;
         self.on("input", |_| { changed=true; });
@@ -213,6 +358,53 @@ impl<'d, 'e, 'f, 'a, 'z, 'c, 'q, EN> Visitor<'d, 'e, EN> where EN:dom::ElementNo
     }
 }
 
+/// A collection of selected values that can be bound with [`Visitor::multi_select`].
+pub trait MultiSelection<T> {
+    /// Whether `value` is selected.
+    fn is_selected(&self, value: &T)->bool;
+    /// Selects or deselects `value`.
+    fn set_selected(&mut self, value: &T, selected: bool);
+}
+
+impl<T: Hash + Eq + Clone> MultiSelection<T> for HashSet<T> {
+    fn is_selected(&self, value: &T)->bool {
+        self.contains(value)
+    }
+    fn set_selected(&mut self, value: &T, selected: bool) {
+        if selected {
+            self.insert(value.clone());
+        } else {
+            self.remove(value);
+        }
+    }
+}
+
+/// Newly selected values are added to the end.
+impl<T: PartialEq + Clone> MultiSelection<T> for Vec<T> {
+    fn is_selected(&self, value: &T)->bool {
+        self.contains(value)
+    }
+    fn set_selected(&mut self, value: &T, selected: bool) {
+        if !selected {
+            self.retain(|v| v!=value);
+        } else if !self.contains(value) {
+            self.push(value.clone());
+        }
+    }
+}
+
+fn hash_key<T: Hash>(t: &T)->u64 {
+    let mut hasher=std::collections::hash_map::DefaultHasher::new();
+    t.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The options without the ones whose value has the same key as an earlier option.
+fn unique_options<T: Hash, L>(options: impl IntoIterator<Item=(T, L)>)->Vec<(T, L)> {
+    let mut keys=HashSet::new();
+    options.into_iter().filter(|o| keys.insert(hash_key(&o.0))).collect()
+}
+
 // Execute wasm tests with `wasm-pack test --node`
 #[cfg(test)]
 mod tests {
@@ -224,6 +416,204 @@ mod tests {
     use wasm_bindgen_futures::JsFuture;
     use wasm_bindgen_test::*;
 
+    #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+    struct Question {
+        id: u32,
+        text: &'static str,
+    }
+
+    #[test]
+    fn select() {
+        let questions = vec![Question { id: 1, text: "Where?" }, Question { id: 2, text: "When?" },
+            Question { id: 3, text: "Why?" }];
+        let mut selected = questions[1].clone();
+        let app = crate::testing::TestApp::new(move |mut root| {
+            if root.button("First").clicked() {
+                selected = questions[0].clone();
+            }
+            root.select(&mut selected, questions.iter().map(|q| (q.clone(), q.text)));
+            root.text(selected.id.to_string().as_str());
+        });
+        let options = || app.find_all_by_tag("option").iter()
+            .map(|o| o.attribute("selected").unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["false", "true", "false"], options());
+        let select = app.find_by_tag("select").unwrap();
+        app.select_option(&select, 2);
+        assert_eq!("3", app.body().children()[2].text_content());
+        assert_eq!(vec!["false", "false", "true"], options());
+        app.click(&app.find_by_text("First").unwrap());
+        assert_eq!(vec!["true", "false", "false"], options());
+        let dnodes = app.dnode(&select).unwrap().child_elements();
+        assert_eq!(vec![Some("true".to_string()), Some("false".to_string()), Some("false".to_string())],
+            dnodes.iter().map(|o| o.attribute("selected")).collect::<Vec<_>>());
+        assert_eq!("Where?", dnodes[0].text_content());
+    }
+
+    /// Selects at most two values.
+    struct AtMostTwo(Vec<u32>);
+    impl MultiSelection<u32> for AtMostTwo {
+        fn is_selected(&self, value: &u32) -> bool {
+            self.0.contains(value)
+        }
+        fn set_selected(&mut self, value: &u32, selected: bool) {
+            if !selected {
+                self.0.retain(|v| v != value);
+            } else if !self.0.contains(value) && self.0.len() < 2 {
+                self.0.push(*value);
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_selection() {
+        let mut selection = AtMostTwo(vec![1, 2]);
+        let mut value = 1;
+        let app = crate::testing::TestApp::new(move |mut root| {
+            root.multi_select(&mut selection, (1..=3).map(|i| (i, i.to_string()))).id("multi");
+            // The last option has the same value as the first one, so it's skipped.
+            root.select(&mut value, [(1, "a"), (2, "b"), (1, "c")]).id("single");
+        });
+        let selected = |id: &str| app.dnode(&app.find_by_id(id).unwrap()).unwrap().child_elements().iter()
+            .map(|o| o.attribute("selected").unwrap()).collect::<Vec<_>>();
+        assert_eq!(vec!["true", "false"], selected("single"));
+        app.toggle_option(&app.find_by_id("multi").unwrap(), 2);
+        assert_eq!(vec!["true", "true", "false"], selected("multi"));
+    }
+
+    #[test]
+    fn multi_select() {
+        let mut labels: HashSet<String> = ["work".to_string()].into_iter().collect();
+        let mut order: Vec<u32> = vec![3, 1];
+        let app = crate::testing::TestApp::new(move |mut root| {
+            root.multi_select(&mut labels, ["home", "work"].iter().map(|l| (l.to_string(), *l))).id("labels");
+            root.multi_select(&mut order, (1..=3).map(|i| (i, i.to_string()))).id("order");
+            let mut sorted: Vec<&String> = labels.iter().collect();
+            sorted.sort();
+            root.text(format!("{:?} {:?}", sorted, order).as_str());
+        });
+        let selected = |id: &str| app.dnode(&app.find_by_id(id).unwrap()).unwrap().child_elements().iter()
+            .map(|o| o.attribute("selected").unwrap()).collect::<Vec<_>>();
+        assert_eq!(vec!["false", "true"], selected("labels"));
+        assert_eq!(Some("true"), app.find_by_id("labels").unwrap().attribute("multiple"));
+        app.toggle_option(&app.find_by_id("labels").unwrap(), 0);
+        app.toggle_option(&app.find_by_id("order").unwrap(), 2);
+        app.toggle_option(&app.find_by_id("order").unwrap(), 1);
+        assert_eq!("[\"home\", \"work\"] [1, 2]", app.body().children()[2].text_content());
+        assert_eq!(vec!["true", "true"], selected("labels"));
+        assert_eq!(vec!["true", "true", "false"], selected("order"));
+        app.toggle_option(&app.find_by_id("labels").unwrap(), 1);
+        assert_eq!("[\"home\"] [1, 2]", app.body().children()[2].text_content());
+    }
+
+    #[test]
+    fn content_editable() {
+        let mut note = "Hello".to_string();
+        let mut html = "<b>bold</b> &amp; <br>plain".to_string();
+        let app = crate::testing::TestApp::new(move |mut root| {
+            if root.button("Clear").clicked() {
+                note.clear();
+                html = "<i title=\"a &quot;b&quot;\">new</i>".to_string();
+            }
+            root.content_editable(&mut note, ContentMode::Text).id("note");
+            root.content_editable(&mut html, ContentMode::Html).id("html");
+            root.text(format!("{}|{}", note, html).as_str());
+        });
+        let (note, html) = (app.find_by_id("note").unwrap(), app.find_by_id("html").unwrap());
+        let dnode = |e| app.dnode(e).unwrap();
+        assert_eq!("Hello", dnode(&note).text_content());
+        assert_eq!("bold & plain", dnode(&html).text_content());
+        assert_eq!("<b>bold</b> &amp; <br>plain", dnode(&html).inner_html());
+
+        app.edit_content(&note, "Hello world", ContentMode::Text);
+        let text_node = dnode(&note).children()[0].clone();
+        app.edit_content(&html, "<b>bold</b> &amp; x", ContentMode::Html);
+        assert_eq!("Hello world|<b>bold</b> &amp; x", app.body().children()[3].text_content());
+        // Rendering again doesn't replace the edited nodes.
+        assert!(text_node == dnode(&note).children()[0]);
+
+        app.click(&app.find_by_text("Clear").unwrap());
+        assert!(dnode(&note).children().is_empty());
+        assert_eq!(Some("a \"b\""), dnode(&html).child_elements()[0].attribute("title").as_deref());
+        assert_eq!("<i title=\"a &quot;b&quot;\">new</i>", dnode(&html).inner_html());
+        assert!(crate::ssr::render_to_string(|mut root| {
+            root.content_editable(&mut "a<b".to_string(), ContentMode::Text);
+        }).contains(">a&lt;b</div>"));
+    }
+
+    #[test]
+    fn content_editable_lines() {
+        let mut note = "first\nsecond".to_string();
+        let app = crate::testing::TestApp::new(move |mut root| {
+            if root.button("Reset").clicked() {
+                note = "1\n\n2".to_string();
+            }
+            root.content_editable(&mut note, ContentMode::Text).id("note");
+            root.text(format!("{:?}", note).as_str());
+        });
+        let note = app.find_by_id("note").unwrap();
+        let dnode = || app.dnode(&note).unwrap();
+        let text = || app.body().children()[2].text_content();
+        assert_eq!("first<br>second", dnode().inner_html());
+        app.edit_content(&note, "first\nsecond\nthird", ContentMode::Text);
+        assert_eq!("\"first\\nsecond\\nthird\"", text());
+        // Pressing enter in the browser adds a `div` for the new line.
+        app.edit_content(&note, "a<div>b</div><div>c<br>d</div><p>e</p>", ContentMode::Html);
+        assert_eq!("\"a\\nb\\nc\\nd\\n\\ne\"", text());
+        app.click(&app.find_by_text("Reset").unwrap());
+        assert_eq!("1<br><br>2", dnode().inner_html());
+        assert_eq!("1\n\n2", crate::dom::ElementNode::inner_text(&dnode()));
+        assert!(crate::ssr::render_to_string(|mut root| {
+            root.content_editable(&mut "a<b\nc".to_string(), ContentMode::Text);
+        }).contains(">a&lt;b<br>c</div>"));
+    }
+
+    #[test]
+    fn focus() {
+        let mut items = vec![(1, "a".to_string()), (2, "b".to_string())];
+        let mut editing = None;
+        let mut edit_text = String::new();
+        let app = crate::testing::TestApp::new(move |mut root| {
+            if root.button("Reverse").clicked() {
+                items.reverse();
+            }
+            root.ul(|ul| {
+                ul.for_each(items.iter_mut(), |i| i.0, "li", |item, li| {
+                    let mut label = li.span(|span| { span.text(item.1.as_str()); });
+                    if label.double_clicked() {
+                        editing = Some(item.0);
+                        edit_text = item.1.clone();
+                    }
+                    li.render_element_if(editing == Some(item.0), "span", |span| {
+                        let mut input = span.text_input(&mut edit_text);
+                        input.autofocus(true);
+                        let focused = input.has_focus();
+                        input.class(if focused { "focused" } else { "" });
+                        if input.lost_focus() {
+                            item.1 = edit_text.clone();
+                            editing = None;
+                        }
+                    });
+                });
+            });
+        });
+        let label = app.find_all_by_tag("span")[0].clone();
+        app.double_click(&label);
+        let input = app.find_by_tag("input").unwrap();
+        assert!(!app.has_focus(&input));
+        app.run_until_stalled();
+        assert!(app.has_focus(&input));
+        assert_eq!(Some("focused"), app.find_by_tag("input").unwrap().attribute("class"));
+        app.input(&input, "aa");
+        // Moving the row keeps the focus of the input.
+        app.click(&app.find_by_text("Reverse").unwrap());
+        app.run_until_stalled();
+        assert!(app.has_focus(&input));
+        assert_eq!("Reverseba", app.body().text_content());
+        app.blur(&input);
+        assert_eq!(None, app.find_by_tag("input"));
+        assert_eq!("Reversebaa", app.body().text_content());
+    }
+
     #[wasm_bindgen_test]
     fn pass() {
         web_sys::console::log_1(&"Testing web_sys".into());
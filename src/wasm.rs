@@ -1,17 +1,70 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::panic;
 use std::rc::Rc;
 
 use super::dom;
+
+type FireEvent = Rc<RefCell<Box<dyn FnMut(u64, String, web_sys::Event)>>>;
+
+thread_local! {
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+    static QUEUED_EVENTS: RefCell<VecDeque<(FireEvent, u64, String, web_sys::Event)>> =
+        RefCell::new(VecDeque::new());
+}
+
+/// Runs `f`, then fires the events that the browser dispatched synchronously meanwhile
+/// (like blur when rendering removes the focused element), because they can't be handled
+/// while the render function is running.
+fn run_and_fire_queued_events(f: impl FnOnce()) {
+    if RUNNING.with(|r| r.replace(true)) {
+        f();
+        return;
+    }
+    f();
+    while let Some((fire_event, uid, name, e))=QUEUED_EVENTS.with(|q| q.borrow_mut().pop_front()) {
+        ((*fire_event).borrow_mut())(uid, name, e);
+    }
+    RUNNING.with(|r| r.set(false));
+}
+
+/// Fires the event, or queues it if an event handler or a frame is running.
+fn fire_or_queue_event(fire_event: &FireEvent, uid: u64, name: String, e: web_sys::Event) {
+    QUEUED_EVENTS.with(|q| q.borrow_mut().push_back((fire_event.clone(), uid, name, e)));
+    run_and_fire_queued_events(|| ());
+}
+
 pub struct WasmEventHandler {
-    closure: Closure<dyn FnMut(web_sys::Event)>
+    closure: Closure<dyn FnMut(web_sys::Event)>,
+    fire_event: FireEvent
+}
+
+/// A `setTimeout` or `setInterval` timer that is cleared when dropped.
+pub struct WasmTimer {
+    handle: i32,
+    repeat: bool,
+    _closure: Closure<dyn FnMut()>
+}
+impl Drop for WasmTimer {
+    fn drop(&mut self) {
+        let window = web_sys::window().unwrap();
+        if self.repeat {
+            window.clear_interval_with_handle(self.handle);
+        } else {
+            window.clear_timeout_with_handle(self.handle);
+        }
+    }
 }
+
 impl dom::EventHandler for WasmEventHandler {
     type ElementNode = web_sys::Element;
     type Event = web_sys::Event;
-    fn new(fire_event: Rc<RefCell<Box<dyn FnMut(u64, String, web_sys::Event)>>>)->Self {
+    type Timer = WasmTimer;
+    fn new(fire_event: FireEvent)->Self {
+        let moved_fire_event=fire_event.clone();
         let closure=Closure::wrap(Box::new(move |e: web_sys::Event| {
-            let el : web_sys::Element=e.target().unwrap().dyn_into().unwrap();
+            // The element with the listener; the target can be one of its children.
+            let el : web_sys::Element=e.current_target().unwrap().dyn_into().unwrap();
             web_sys::console::log_2(&"in event handler data-uid string=".to_string().into(),
                 &el.get_attribute("data-uid").unwrap().to_string().into());
             let uid:u64=el.get_attribute("data-uid").unwrap().parse::<u64>().unwrap();
@@ -21,19 +74,75 @@ impl dom::EventHandler for WasmEventHandler {
             web_sys::console::log_2(&"in event handler data-uid=".to_string().into(),
                 &uid.to_string().into());
             // e.prevent_default();
-        ((*fire_event).borrow_mut())(uid, name.to_string(), e);
+            if let Some(drag)=e.dyn_ref::<web_sys::DragEvent>() {
+                // Firefox only starts dragging if some data is set.
+                if name=="dragstart" {
+                    if let Some(data_transfer)=drag.data_transfer() {
+                        data_transfer.set_data("text/plain", "").unwrap();
+                    }
+                }
+            }
+        fire_or_queue_event(&moved_fire_event, uid, name, e);
         })  as Box<dyn FnMut(_)>);
-        Self {closure}
+        Self {closure, fire_event}
     }
     fn create_event_listener(&self, e: &Self::ElementNode, name: String) {
         e.add_event_listener_with_callback(
                 name.as_str(), self.closure.as_ref().unchecked_ref()).unwrap();
     }
+    fn create_timer(&self, uid: u64, name: String, interval: std::time::Duration, repeat: bool)->WasmTimer {
+        let fire_event=self.fire_event.clone();
+        let closure=Closure::wrap(Box::new(move || {
+            let e=web_sys::Event::new(name.as_str()).unwrap();
+            fire_or_queue_event(&fire_event, uid, name.clone(), e);
+        }) as Box<dyn FnMut()>);
+        let window = web_sys::window().unwrap();
+        let timeout=interval.as_millis().min(i32::MAX as u128) as i32;
+        let handle=if repeat {
+            window.set_interval_with_callback_and_timeout_and_arguments_0(
+                closure.as_ref().unchecked_ref(), timeout).unwrap()
+        } else {
+            window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                closure.as_ref().unchecked_ref(), timeout).unwrap()
+        };
+        WasmTimer {handle, repeat, _closure: closure}
+    }
+    fn now(&self)->std::time::Duration {
+        let ms=web_sys::window().unwrap().performance().unwrap().now();
+        std::time::Duration::from_secs_f64(ms/1000.0)
+    }
+    /// Renders are batched into animation frames, which are synced to the display and paused in
+    /// background tabs.
+    fn default_scheduler()->Option<Rc<dyn dom::Scheduler>> {
+        Some(Rc::new(AnimationFrameScheduler {}))
+    }
 }
 
 
 
 
+/// Runs futures on the browser's microtask queue using `wasm_bindgen_futures`.
+pub struct WasmExecutor {
+}
+impl dom::Executor for WasmExecutor {
+    fn new()->Self {
+        Self {}
+    }
+    fn spawn_local(&self, future: dom::LocalFuture) {
+        wasm_bindgen_futures::spawn_local(future);
+    }
+}
+
+/// Runs the batched renders before the next repaint using `requestAnimationFrame`.
+pub struct AnimationFrameScheduler {
+}
+impl dom::Scheduler for AnimationFrameScheduler {
+    fn request_frame(&self, callback: Box<dyn FnOnce()>) {
+        let closure=Closure::once_into_js(move |_: f64| run_and_fire_queued_events(callback));
+        web_sys::window().unwrap().request_animation_frame(closure.unchecked_ref()).unwrap();
+    }
+}
+
 impl dom::Document for web_sys::Document {
     type TextNode=web_sys::Text;
     type ElementNode=web_sys::Element;
@@ -46,8 +155,12 @@ impl dom::Document for web_sys::Document {
         self.create_text_node(text)
     }
     
-    fn create_element(&self, tag: &'static str)->web_sys::Element {
-        self.create_element(tag).unwrap()
+    fn create_element(&self, tag: impl Into<dom::Name>)->web_sys::Element {
+        self.create_element(&tag.into()).unwrap()
+    }
+    fn active_element(&self)->Option<web_sys::Element> {
+        let body: Option<web_sys::Element>=self.body().map(Into::into);
+        web_sys::Document::active_element(self).filter(|e| Some(e)!=body.as_ref())
     }
     fn log_1(s: &str) {
         web_sys::console::log_1(&s.into());
@@ -63,6 +176,9 @@ impl dom::TextNode for web_sys::Text {
         let document = window.document().unwrap();
         document.create_text_node(text)
     }
+    fn text_data(&self)->String {
+        self.data()
+    }
 }
 
 
@@ -75,6 +191,9 @@ impl dom::GenericNode for web_sys::Node {
     fn into_element_node(self)->Self::ElementNode {
         self.dyn_into().unwrap()
     }
+    fn is_element(&self)->bool {
+        self.node_type()==web_sys::Node::ELEMENT_NODE
+    }
 }
 
 
@@ -82,12 +201,47 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlElement;
 use web_sys::HtmlInputElement;
+use web_sys::HtmlOptionElement;
 
 
 impl dom::Event for web_sys::Event {
     fn prevent_default(&self) {
         web_sys::Event::prevent_default(&self)
     }
+    fn keyboard(&self)->Option<crate::keyboard::KeyboardEvent> {
+        let e=self.dyn_ref::<web_sys::KeyboardEvent>()?;
+        Some(crate::keyboard::KeyboardEvent {
+            key: e.key().as_str().into(),
+            code: e.code(),
+            modifiers: crate::keyboard::Modifiers {ctrl: e.ctrl_key(), shift: e.shift_key(), alt: e.alt_key(),
+                meta: e.meta_key()},
+            repeat: e.repeat(),
+        })
+    }
+    fn pointer(&self)->Option<crate::pointer::PointerEvent> {
+        let e=self.dyn_ref::<web_sys::MouseEvent>()?;
+        let pointer_type=self.dyn_ref::<web_sys::PointerEvent>()
+            .map_or(crate::pointer::PointerType::Mouse, |p| p.pointer_type().as_str().into());
+        let wheel_delta=self.dyn_ref::<web_sys::WheelEvent>().map_or((0.0, 0.0), |w| {
+            // Lines and pages are converted to pixels with typical sizes.
+            let scale=match w.delta_mode() {
+                web_sys::WheelEvent::DOM_DELTA_LINE=>16.0,
+                web_sys::WheelEvent::DOM_DELTA_PAGE=>800.0,
+                _=>1.0,
+            };
+            (w.delta_x()*scale, w.delta_y()*scale)
+        });
+        Some(crate::pointer::PointerEvent {
+            client: (e.client_x() as f64, e.client_y() as f64),
+            offset: (e.offset_x() as f64, e.offset_y() as f64),
+            button: crate::pointer::MouseButton::from_index(e.button()).filter(|_| !e.type_().ends_with("move")),
+            buttons: e.buttons(),
+            modifiers: crate::keyboard::Modifiers {ctrl: e.ctrl_key(), shift: e.shift_key(), alt: e.alt_key(),
+                meta: e.meta_key()},
+            pointer_type,
+            wheel_delta,
+        })
+    }
 }
 
 impl dom::ElementNode for web_sys::Element {
@@ -96,6 +250,7 @@ impl dom::ElementNode for web_sys::Element {
     type GenericNode = web_sys::Node;
     type EventHandler = WasmEventHandler;
     type Event = web_sys::Event;
+    type Executor = WasmExecutor;
     fn replace_text_child(&self, new: &Self::TextNode, old: &Self::TextNode) {
         self.replace_child(new, old).unwrap();
     }
@@ -112,12 +267,18 @@ impl dom::ElementNode for web_sys::Element {
     fn remove_child(&self, child: &Self) {
         web_sys::Node::remove_child(self, child).unwrap();
     }
+    fn remove_child_node(&self, child: &web_sys::Node) {
+        web_sys::Node::remove_child(self, child).unwrap();
+    }
     fn prepend_child(&self, child: &Self) {
         self.insert_before(child, self.first_child().as_ref()).unwrap();
     }
     fn append_text_child(&self, child: &Self::TextNode) {
         self.insert_before(child, None).unwrap();
     }
+    fn insert_text_child_before(&self, child: &Self::TextNode, next_sibling: Option<&web_sys::Node>) {
+        self.insert_before(child, next_sibling).unwrap();
+    }
     fn set_attribute(&self, name: &str, value: &str) {
         if name=="checked" {
             let e: &web_sys::HtmlInputElement= self.dyn_ref().unwrap();
@@ -125,6 +286,8 @@ impl dom::ElementNode for web_sys::Element {
         } else if name=="value" {
             let e: &web_sys::HtmlInputElement= self.dyn_ref().unwrap();
             e.set_value(value);
+        } else if let (true, Some(option))=(name=="selected", self.dyn_ref::<HtmlOptionElement>()) {
+            option.set_selected(value=="true");
          } else {
             web_sys::Element::set_attribute(self, name, value).unwrap();
         }
@@ -137,22 +300,26 @@ impl dom::ElementNode for web_sys::Element {
                 return input_element.value();
             }
         }
-        return web_sys::Element::get_attribute(self, name).unwrap();
+        if let (true, Some(option))=(name=="selected", self.dyn_ref::<HtmlOptionElement>()) {
+            return option.selected().to_string();
+        }
+        return web_sys::Element::get_attribute(self, name).unwrap_or_default();
     }
     fn remove(&self) {
         web_sys::Element::remove(&self);
     }
-    fn new(tag: &'static str)->Self {
+    fn new(tag: impl Into<dom::Name>)->Self {
         let window = web_sys::window().unwrap();
         let document = window.document().unwrap();
-        document.create_element(tag).unwrap()
+        document.create_element(&tag.into()).unwrap()
     }
-    fn create_dnode_event_listener(&self, f : Rc<RefCell<dyn FnMut(u64, &'static str)>>, uid:u64, name:&'static str) {
+    fn create_dnode_event_listener(&self, f : Rc<RefCell<dyn FnMut(u64, String)>>, uid:u64, name:&str) {
+        let event_name = name.to_string();
         let closure = Closure::wrap(Box::new(move |e: Self::Event| {
-            web_sys::console::log_2(&uid.to_string().into(), &name.into());
+            web_sys::console::log_2(&uid.to_string().into(), &event_name.as_str().into());
             web_sys::console::log_2(&"e=".to_string().into(), &e.type_().into());
 
-            f.borrow_mut()(uid, name);
+            f.borrow_mut()(uid, event_name.clone());
             // e.prevent_default();
         })  as Box<dyn FnMut(_)>);
         self.add_event_listener_with_callback(name, closure.as_ref().unchecked_ref()).unwrap();
@@ -182,12 +349,39 @@ impl dom::ElementNode for web_sys::Element {
     fn set_text_content(&self, s:&str) {
         web_sys::Node::set_text_content(self, Some(s));
     }
+    fn text_content(&self)->String {
+        web_sys::Node::text_content(self).unwrap_or_default()
+    }
+    fn inner_text(&self)->String {
+        match self.dyn_ref::<HtmlElement>() {
+            Some(e)=>e.inner_text(),
+            None=>web_sys::Node::text_content(self).unwrap_or_default(),
+        }
+    }
+    fn set_inner_text(&self, s: &str) {
+        match self.dyn_ref::<HtmlElement>() {
+            Some(e)=>e.set_inner_text(s),
+            None=>web_sys::Node::set_text_content(self, Some(s)),
+        }
+    }
+    fn inner_html(&self)->String {
+        web_sys::Element::inner_html(self)
+    }
+    fn set_inner_html(&self, html: &str) {
+        web_sys::Element::set_inner_html(self, html);
+    }
     fn into_generic_node(&self)->&Self::GenericNode {
         self.as_ref()
     }
     fn focus(&self) {
         self.dyn_ref::<HtmlElement>().unwrap().focus().unwrap();
     }
+    fn has_focus(&self)->bool {
+        self.owner_document().and_then(|d| d.active_element()).is_some_and(|e| e==*self)
+    }
+    fn tag_name(&self)->String {
+        web_sys::Element::tag_name(self)
+    }
 }
 
 pub fn render<F>(f:F) where F:FnMut(super::Visitor<web_sys::Element>) + 'static {
@@ -196,4 +390,24 @@ pub fn render<F>(f:F) where F:FnMut(super::Visitor<web_sys::Element>) + 'static
     let body = document.body().unwrap();
     let body=web_sys::Element::from(body);
     super::EDOM::render(body, f);
+}
+
+/// Like [`render`], but the DOM updates caused by events are batched into one render per animation
+/// frame (see [`super::EDOM::set_scheduler`]).
+pub fn render_batched<F>(f:F) where F:FnMut(super::Visitor<web_sys::Element>) + 'static {
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let body = document.body().unwrap();
+    let body=web_sys::Element::from(body);
+    let edom=super::EDOM::render(body, f);
+    edom.borrow_mut().set_scheduler(Some(Rc::new(AnimationFrameScheduler {})));
+}
+
+/// Attaches the application to the server rendered children of `body` (see [`super::EDOM::hydrate`]).
+pub fn hydrate<F>(f:F) where F:FnMut(super::Visitor<web_sys::Element>) + 'static {
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let body = document.body().unwrap();
+    let body=web_sys::Element::from(body);
+    super::EDOM::hydrate(body, f);
 }
\ No newline at end of file